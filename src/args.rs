@@ -1,6 +1,8 @@
+use std::time::Duration;
+
 use clap::{
     error::ErrorKind, Arg, ArgAction, ArgMatches, Args, Command, Error, FromArgMatches, Parser,
-    Subcommand,
+    Subcommand, ValueEnum,
 };
 
 #[derive(Debug, Parser)]
@@ -8,18 +10,260 @@ use clap::{
 pub struct CaruArgs {
     #[command(subcommand)]
     pub command: BasicCommand,
+    /// Profile to use instead of the default profile
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    /// Seconds to wait for other caru processes to finish with the game
+    #[arg(long, global = true, value_name = "SECONDS", default_value_t = 10)]
+    pub lock_timeout: u64,
+    /// Format of the printed results, json and csv are meant for scripts
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable text
+    Text,
+    /// One JSON document per command, see `output.rs` for the schema
+    Json,
+    /// A header row followed by one row per entity, upgrade, profile, ...
+    Csv,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum BasicCommand {
-    /// Initializes a new game at <User>/.caru
+    /// Initializes a new game for the profile at <User>/.caru
     Init,
-    /// Delete all data relating to the current game instance
-    Delete,
+    /// Delete all data relating to the current profile
+    Delete(DeleteArgs),
+    /// Manages the profiles, each profile is a separate game
+    Profile(ProfileArgs),
     /// Gets the current status of the game
     Status,
+    /// Works for a minute of income, regular work builds a combo
+    #[command(alias = "click")]
+    Work,
+    /// Plays the game in a live full screen view, buying upgrades with single keys
+    Play,
     /// Upgrades
+    #[command(
+        subcommand_value_name = "ENTITY",
+        after_help = "Every entity of the catalog can be referenced by its name, e.g. `caru upgrade lumberjack --one`"
+    )]
     Upgrade(UpgradeEntityArgs),
+    /// Sells levels of an entity for a share of what they cost
+    Sell(SellArgs),
+    /// Ranks the entities by how fast their next upgrade pays for itself
+    Advise(AdviseArgs),
+    /// Forecasts the game on a copy of the save, which is left untouched
+    Simulate(SimulateArgs),
+    /// Lists the achievements and the progress towards them
+    Achievements,
+    /// Spends the research points of the Academics on the tech tree
+    Research(ResearchArgs),
+    /// Sends the army of Bowmakers, Weaponsmiths and Catapults to conquer regions
+    Campaign(CampaignArgs),
+    /// Proclaims the policies of the King, his level unlocks the slots
+    Edict(EdictArgs),
+    /// Gives rules to the managers unlocked by research, they buy upgrades also while away
+    Manager(ManagerArgs),
+    /// Resets all entities and gold in exchange for a permanent gold per second bonus
+    Prestige(PrestigeArgs),
+    /// Manages the save file
+    Save(SaveArgs),
+    /// Rolls the save back to a backup
+    Restore(RestoreArgs),
+}
+
+#[derive(Debug, Args)]
+#[command(group(clap::ArgGroup::new("restore").args(["list", "backup"]).required(true)))]
+pub struct RestoreArgs {
+    /// Lists all backups, newest first
+    #[arg(short, long)]
+    pub list: bool,
+    /// Name of the backup to restore
+    pub backup: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SaveArgs {
+    #[command(subcommand)]
+    pub command: SaveCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SaveCommand {
+    /// Upgrades the save file to the current save format
+    Migrate(MigrateArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    /// Only reports what would change without writing the save file
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ResearchArgs {
+    #[command(subcommand)]
+    pub command: ResearchCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ResearchCommand {
+    /// Lists all nodes of the tech tree
+    List,
+    /// Shows the effect and requirements of a node
+    Info { node: String },
+    /// Researches a node
+    Buy { node: String },
+}
+
+#[derive(Debug, Args)]
+pub struct CampaignArgs {
+    #[command(subcommand)]
+    pub command: CampaignCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CampaignCommand {
+    /// Lists the regions and the chance to conquer them
+    List,
+    /// Starts a campaign, it is decided once its time is up
+    Start { region: String },
+}
+
+#[derive(Debug, Args)]
+pub struct EdictArgs {
+    #[command(subcommand)]
+    pub command: EdictCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum EdictCommand {
+    /// Lists the edicts and the unlocked slots
+    List,
+    /// Proclaims an edict in a free slot or replaces the edict in a slot
+    Set {
+        edict: String,
+        /// Slot to replace, starting at 1
+        #[arg(short, long)]
+        slot: Option<usize>,
+    },
+    /// Revokes an edict
+    Clear { edict: String },
+}
+
+#[derive(Debug, Args)]
+pub struct ManagerArgs {
+    #[command(subcommand)]
+    pub command: ManagerCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ManagerCommand {
+    /// Lists the managers, their rules and their latest purchases
+    List,
+    /// Keeps an entity within some levels of another entity
+    Keep {
+        entity: String,
+        /// Levels the entity may fall behind
+        #[arg(long, value_name = "LEVELS")]
+        within: u32,
+        /// Entity to keep up with
+        #[arg(long, value_name = "ENTITY")]
+        of: String,
+    },
+    /// Spends a share of the gold on an entity while its upgrades pay back fast enough
+    Spend {
+        entity: String,
+        /// Share of the gold to spend in every round
+        #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u32).range(1..=100))]
+        share: u32,
+        /// Longest payback time of an upgrade, e.g. 1h or 30m
+        #[arg(long, value_parser = parse_duration)]
+        payback: Duration,
+    },
+    /// Removes all rules of the manager of an entity
+    Dismiss { entity: String },
+}
+
+#[derive(Debug, Args)]
+pub struct PrestigeArgs {
+    /// Only shows the payout without resetting the game
+    #[arg(short, long)]
+    pub preview: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DeleteArgs {
+    /// Delete the whole game directory including all profiles
+    #[arg(long)]
+    pub all: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ProfileArgs {
+    #[command(subcommand)]
+    pub command: ProfileCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileCommand {
+    /// Lists all profiles
+    List,
+    /// Creates a profile with a new game
+    Create { name: String },
+    /// Copies a profile into a new profile
+    Copy { source: String, target: String },
+    /// Deletes a profile and its backups
+    Delete { name: String },
+    /// Sets the profile that is used when no profile is given
+    Switch { name: String },
+}
+
+#[derive(Debug, Args)]
+pub struct SellArgs {
+    /// Entity to sell levels of
+    pub entity: String,
+    /// Number of levels to sell, at most the levels the entity has
+    #[arg(short = 'n', long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub amount: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct AdviseArgs {
+    /// Number of levels to buy at once
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub levels: u32,
+    /// Plans the purchases for this duration, e.g. 8h or 1h30m
+    #[arg(long, value_parser = parse_duration)]
+    pub horizon: Option<Duration>,
+}
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    /// Simulated duration, e.g. 24h or 1d12h
+    #[arg(long = "for", value_name = "DURATION", value_parser = parse_duration)]
+    pub duration: Duration,
+    /// Decides what to buy after every step
+    #[arg(short, long, value_enum, default_value_t = SimulationStrategyArg::Greedy)]
+    pub strategy: SimulationStrategyArg,
+    /// Simulated time between two rounds of purchases
+    #[arg(long, value_parser = parse_duration, default_value = "1m")]
+    pub step: Duration,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SimulationStrategyArg {
+    /// Buy the upgrades with the best gold per second per gold spent
+    Greedy,
+    /// Always buy the cheapest upgrade
+    Cheapest,
+    /// Buy nothing and save up the gold
+    None,
 }
 
 #[derive(Debug, Args)]
@@ -32,21 +276,27 @@ pub struct UpgradeEntityArgs {
 #[derive(Debug, Subcommand)]
 pub enum IdleEntityArg {
     /// Upgrade all possible entities
-    All,
-    /// Reference the Lumberjack entity
-    Lumberjack(IdleEntityAmendmentArg),
-    /// Reference the Stonemason entity
-    Stonemason(IdleEntityAmendmentArg),
-    /// Reference the Bowmaker entity
-    Bowmaker(IdleEntityAmendmentArg),
-    /// Reference the Weaponsmith entity
-    Weaponsmith(IdleEntityAmendmentArg),
-    /// Reference the Academic entity
-    Academic(IdleEntityAmendmentArg),
-    /// Reference the Catapult entity
-    Catapult(IdleEntityAmendmentArg),
-    /// Reference the King entity
-    King(IdleEntityAmendmentArg),
+    All(UpgradeAllArgs),
+    /// Reference an entity of the catalog by its name, e.g. `upgrade lumberjack -o`
+    #[command(external_subcommand)]
+    Entity(Vec<String>),
+}
+
+#[derive(Debug, Args)]
+pub struct UpgradeAllArgs {
+    /// Decides which entity to upgrade next
+    #[arg(short, long, value_enum, default_value_t = SpendingStrategyArg::Cheapest)]
+    pub strategy: SpendingStrategyArg,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SpendingStrategyArg {
+    /// Always buy the cheapest upgrade
+    Cheapest,
+    /// Buy the upgrade with the best gold per second per gold spent
+    Efficiency,
+    /// Upgrade the entities in turn
+    RoundRobin,
 }
 
 #[derive(Debug)]
@@ -60,6 +310,21 @@ pub enum EntityCountArg {
     One,
     All,
     Amount(u32),
+    /// Up to the next milestone level
+    Milestone,
+}
+
+impl IdleEntityAmendmentArg {
+    /// Parses the arguments of an entity subcommand, where the first argument
+    /// is the name of the entity. Returns the entity name and its amendment.
+    pub fn parse_entity_command(args: &[String]) -> Result<(String, Self), Error> {
+        let entity_name = args.first().cloned().unwrap_or_default();
+        let cmd = Self::augment_args(Command::new("upgrade"))
+            .bin_name(format!("caru upgrade {}", entity_name));
+        let matches = cmd.try_get_matches_from(args)?;
+
+        Ok((entity_name, Self::from_arg_matches(&matches)?))
+    }
 }
 
 impl Args for IdleEntityAmendmentArg {
@@ -86,6 +351,13 @@ impl Args for IdleEntityAmendmentArg {
                 .help("Sets the amount to a user defined value")
                 .action(ArgAction::Set),
         )
+        .arg(
+            Arg::new("milestone")
+                .short('m')
+                .long("milestone")
+                .help("Sets the amount to the levels missing to the next milestone")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("info")
                 .short('i')
@@ -95,7 +367,7 @@ impl Args for IdleEntityAmendmentArg {
         )
         .group(
             clap::ArgGroup::new("entity_amount")
-                .args(["one", "all", "amount", "info"])
+                .args(["one", "all", "amount", "milestone", "info"])
                 .required(true),
         )
     }
@@ -123,6 +395,13 @@ impl Args for IdleEntityAmendmentArg {
                 .help("Sets the amount to a user defined value")
                 .action(ArgAction::Set),
         )
+        .arg(
+            Arg::new("milestone")
+                .short('m')
+                .long("milestone")
+                .help("Sets the amount to the levels missing to the next milestone")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("info")
                 .short('i')
@@ -132,7 +411,7 @@ impl Args for IdleEntityAmendmentArg {
         )
         .group(
             clap::ArgGroup::new("entity_amount")
-                .args(["one", "all", "amount", "info"])
+                .args(["one", "all", "amount", "milestone", "info"])
                 .required(true),
         )
     }
@@ -157,6 +436,11 @@ impl FromArgMatches for IdleEntityAmendmentArg {
                 info: false,
                 count: Some(EntityCountArg::All),
             });
+        } else if matches.get_flag("milestone") {
+            return Ok(Self {
+                info: false,
+                count: Some(EntityCountArg::Milestone),
+            });
         } else if let Some(n) = matches.get_one::<u32>("amount") {
             return Ok(Self {
                 info: false,
@@ -171,3 +455,61 @@ impl FromArgMatches for IdleEntityAmendmentArg {
         Ok(())
     }
 }
+
+/// Parses durations like `90s`, `45m`, `8h` or `1d12h`
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for character in value.chars() {
+        if character.is_ascii_digit() {
+            number.push(character);
+            continue;
+        }
+
+        let unit = match character {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            _ => return Err(format!("unknown unit '{}', use s, m, h or d", character)),
+        };
+        let amount: u64 = number
+            .parse()
+            .map_err(|_| format!("missing number before '{}'", character))?;
+        seconds = amount
+            .checked_mul(unit)
+            .and_then(|unit_seconds| seconds.checked_add(unit_seconds))
+            .ok_or("duration is too long")?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(format!("missing unit after {}, use s, m, h or d", number));
+    }
+    if seconds == 0 {
+        return Err("duration must be longer than 0s".to_string());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::parse_duration;
+
+    #[test]
+    fn durations_combine_units() {
+        assert_eq!(Ok(Duration::from_secs(90)), parse_duration("90s"));
+        assert_eq!(Ok(Duration::from_secs(8 * 3_600)), parse_duration("8h"));
+        assert_eq!(Ok(Duration::from_secs(5_400)), parse_duration("1h30m"));
+        assert_eq!(Ok(Duration::from_secs(129_600)), parse_duration("1d12h"));
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        for value in ["", "8", "h", "0h", "8x", "99999999999999999999d"] {
+            assert!(parse_duration(value).is_err(), "{}", value);
+        }
+    }
+}
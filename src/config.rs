@@ -1,73 +1,587 @@
 use std::{
     fs,
     fs::File,
-    io::{self, BufReader},
-    path::PathBuf,
+    io::{self, BufReader, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use crate::game::Game;
+use serde_json::Value;
+
+use crate::{
+    game::{catalog::Catalog, Game},
+    save::{self, MigrationReport},
+};
 
 const GAME_FOLDER_NAME: &str = ".caru";
-const GAME_FILE_NAME: &str = "game.json";
+/// Save file of caru versions without profiles, adopted as the default profile
+const LEGACY_GAME_FILE_NAME: &str = "game.json";
+const PROFILES_FOLDER_NAME: &str = "profiles";
+pub const PROFILE_FILE_EXTENSION: &str = ".json";
+const DEFAULT_PROFILE_FILE_NAME: &str = "default_profile";
+pub const DEFAULT_PROFILE: &str = "default";
+const CATALOG_FILE_NAME: &str = "catalog.json";
+const LOCK_FILE_NAME: &str = "caru.lock";
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+const BACKUP_FOLDER_NAME: &str = "backups";
+const BACKUP_FILE_PREFIX: &str = "game-";
+const BACKUP_FILE_EXTENSION: &str = ".json";
+/// Number of previous saves kept in the backup directory
+const MAX_BACKUPS: usize = 10;
 
 pub struct Config {
+    /// Name of the profile whose save is used
+    pub profile: String,
     pub game_directory: PathBuf,
+    pub profiles_directory: PathBuf,
+    /// Holds the name of the profile used when no profile is given
+    pub default_profile_file_path: PathBuf,
     pub game_file_path: PathBuf,
+    /// Optional user override of the built-in entity catalog
+    pub catalog_file_path: PathBuf,
+    pub backup_directory: PathBuf,
+    pub lock_file_path: PathBuf,
+}
+
+/// Exclusive access to the game directory, released when dropped
+pub struct GameLock {
+    _file: File,
+}
+
+/// A previous save in the backup directory
+pub struct Backup {
+    pub name: String,
+    pub path: PathBuf,
+    pub created: SystemTime,
 }
 
 impl Config {
-    pub fn build(home_dir: &PathBuf) -> Config {
+    /// Builds the config for `profile`, or the persisted default profile
+    pub fn build(home_dir: &PathBuf, profile: Option<&str>) -> io::Result<Config> {
         let mut game_dir = PathBuf::new();
         game_dir.push(home_dir);
         game_dir.push(GAME_FOLDER_NAME);
 
-        let mut game_file_path = PathBuf::from(&game_dir);
-        game_file_path.push(GAME_FILE_NAME);
+        let mut default_profile_file_path = PathBuf::from(&game_dir);
+        default_profile_file_path.push(DEFAULT_PROFILE_FILE_NAME);
+
+        let profile = match profile {
+            Some(profile) => profile.to_string(),
+            None => read_default_profile(&default_profile_file_path)?,
+        };
+        validate_profile_name(&profile)?;
+
+        Ok(Config::for_profile(game_dir, profile))
+    }
+
+    fn for_profile(game_dir: PathBuf, profile: String) -> Config {
+        let mut profiles_directory = PathBuf::from(&game_dir);
+        profiles_directory.push(PROFILES_FOLDER_NAME);
+
+        let mut default_profile_file_path = PathBuf::from(&game_dir);
+        default_profile_file_path.push(DEFAULT_PROFILE_FILE_NAME);
+
+        let mut game_file_path = PathBuf::from(&profiles_directory);
+        game_file_path.push(format!("{}{}", profile, PROFILE_FILE_EXTENSION));
+
+        let mut catalog_file_path = PathBuf::from(&game_dir);
+        catalog_file_path.push(CATALOG_FILE_NAME);
+
+        let mut backup_directory = PathBuf::from(&game_dir);
+        backup_directory.push(BACKUP_FOLDER_NAME);
+        backup_directory.push(&profile);
+
+        let mut lock_file_path = PathBuf::from(&game_dir);
+        lock_file_path.push(LOCK_FILE_NAME);
         Config {
+            profile,
             game_directory: game_dir,
-            game_file_path: game_file_path,
+            profiles_directory,
+            default_profile_file_path,
+            game_file_path,
+            catalog_file_path,
+            backup_directory,
+            lock_file_path,
         }
     }
 
-    pub fn load(&self) -> Result<Game, io::Error> {
-        let file = File::open(&self.game_file_path)?;
-        let reader = BufReader::new(file);
+    /// The same config, but for another profile
+    pub fn with_profile(&self, profile: &str) -> io::Result<Config> {
+        validate_profile_name(profile)?;
+        Ok(Config::for_profile(
+            self.game_directory.clone(),
+            profile.to_string(),
+        ))
+    }
 
-        // Read the JSON contents of the file as an instance of `Game`.
-        let game = serde_json::from_reader(reader)?;
+    /// Name of the profile used when no profile is given
+    pub fn default_profile(&self) -> io::Result<String> {
+        read_default_profile(&self.default_profile_file_path)
+    }
+
+    pub fn profile_exists(&self) -> bool {
+        self.game_file_path.exists()
+    }
+
+    /// Moves the save of caru versions without profiles, and its backups, to
+    /// the default profile
+    pub fn adopt_legacy_save(&self) -> io::Result<()> {
+        let mut legacy_game_file_path = PathBuf::from(&self.game_directory);
+        legacy_game_file_path.push(LEGACY_GAME_FILE_NAME);
+        let default_config = self.with_profile(DEFAULT_PROFILE)?;
+        if !legacy_game_file_path.exists() || default_config.profile_exists() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.profiles_directory)?;
+        fs::rename(&legacy_game_file_path, &default_config.game_file_path)?;
+
+        let legacy_backup_directory = default_config
+            .backup_directory
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let legacy_backups = legacy_backup_directory_entries(&legacy_backup_directory)?;
+        if !legacy_backups.is_empty() {
+            fs::create_dir_all(&default_config.backup_directory)?;
+        }
+        for legacy_backup in legacy_backups {
+            let mut backup_path = PathBuf::from(&default_config.backup_directory);
+            backup_path.push(legacy_backup.file_name().unwrap_or_default());
+            fs::rename(&legacy_backup, backup_path)?;
+        }
+        Ok(())
+    }
+
+    /// Takes an advisory lock on the game directory, waiting up to `timeout`
+    /// for other caru processes to release it
+    pub fn lock(&self, timeout: Duration) -> io::Result<GameLock> {
+        if !self.game_directory.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "There is no game at {}, run `caru init` first",
+                    self.game_directory.display()
+                ),
+            ));
+        }
+
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.lock_file_path)?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(GameLock { _file: file }),
+                Err(fs::TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(fs::TryLockError::WouldBlock) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WouldBlock,
+                        format!(
+                        "Another caru process is using the game at {}, gave up after waiting {}s",
+                        self.game_directory.display(),
+                        timeout.as_secs_f64()
+                    ),
+                    ))
+                }
+                Err(fs::TryLockError::Error(err)) => return Err(err),
+            }
+        }
+    }
+
+    /// Loads the user catalog from the game directory if there is one,
+    /// otherwise the built-in catalog
+    pub fn load_catalog(&self) -> Result<Catalog, io::Error> {
+        if !self.catalog_file_path.exists() {
+            return Ok(Catalog::builtin());
+        }
+
+        let json = fs::read_to_string(&self.catalog_file_path)?;
+        Catalog::from_json(&json).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid catalog at {}: {}",
+                    self.catalog_file_path.display(),
+                    err
+                ),
+            )
+        })
+    }
+
+    pub fn load(&self, catalog: &Catalog) -> Result<Game, io::Error> {
+        // Older saves are migrated to the current version before they are
+        // read as an instance of `Game`.
+        let mut game = save::from_document(self.read_document()?)?;
+        game.sync_catalog(catalog);
 
         Ok(game)
     }
 
     pub fn save(&self, game: Game) -> io::Result<()> {
-        let serialized_game = serde_json::to_string(&game).unwrap();
-        fs::write(&self.game_file_path, &serialized_game.as_bytes())?;
+        self.write_document(&save::to_document(&game)?)
+    }
+
+    /// Migrates the save file to the current version. With `dry_run` the
+    /// file is left untouched and only the report is returned.
+    pub fn migrate(&self, dry_run: bool) -> io::Result<MigrationReport> {
+        let mut document = self.read_document()?;
+        let report = save::migrate(&mut document)?;
+
+        if !dry_run && !report.is_up_to_date() {
+            // Make sure the migrated document is a valid game before replacing the save
+            save::from_document(document.clone())?;
+            self.write_document(&document)?;
+        }
+        Ok(report)
+    }
+
+    fn read_document(&self) -> io::Result<Value> {
+        let file = File::open(&self.game_file_path).map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "There is no save for the profile {}, run `caru --profile {} init` first",
+                    self.profile, self.profile
+                ),
+            ),
+            _ => err,
+        })?;
+        let reader = BufReader::new(file);
+
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Replaces the save file without ever leaving a partially written file
+    /// behind. The previous save is kept as backup.
+    fn write_document(&self, document: &Value) -> io::Result<()> {
+        let serialized_game = serde_json::to_string(document)?;
+
+        if self.game_file_path.exists() {
+            self.backup_current_save()?;
+        }
+        fs::create_dir_all(&self.profiles_directory)?;
+        write_atomically(&self.game_file_path, serialized_game.as_bytes())
+    }
+
+    fn backup_current_save(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.backup_directory)?;
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        // Zero padded, so that sorting by name sorts by creation time. Saves
+        // within the same millisecond are told apart by a sequence number.
+        let backup_path = (0..)
+            .map(|sequence: u32| {
+                let mut backup_path = PathBuf::from(&self.backup_directory);
+                backup_path.push(format!(
+                    "{}{:020}-{:04}{}",
+                    BACKUP_FILE_PREFIX, millis, sequence, BACKUP_FILE_EXTENSION
+                ));
+                backup_path
+            })
+            .find(|backup_path| !backup_path.exists())
+            .unwrap();
+        fs::copy(&self.game_file_path, &backup_path)?;
+
+        for backup in self.list_backups()?.iter().skip(MAX_BACKUPS) {
+            fs::remove_file(&backup.path)?;
+        }
+        Ok(())
+    }
+
+    /// All backups, newest first
+    pub fn list_backups(&self) -> io::Result<Vec<Backup>> {
+        if !self.backup_directory.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut backups = Vec::new();
+        for dir_entry in fs::read_dir(&self.backup_directory)? {
+            let path = dir_entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(millis) = name
+                .strip_prefix(BACKUP_FILE_PREFIX)
+                .and_then(|name| name.strip_suffix(BACKUP_FILE_EXTENSION))
+                // Older backups have no sequence number
+                .and_then(|stem| stem.split('-').next())
+                .and_then(|millis| millis.parse::<u64>().ok())
+            else {
+                continue;
+            };
+
+            backups.push(Backup {
+                name: name.to_string(),
+                created: UNIX_EPOCH + Duration::from_millis(millis),
+                path: path.clone(),
+            });
+        }
+        backups.sort_by(|a, b| b.name.cmp(&a.name));
+        Ok(backups)
+    }
+
+    /// Replaces the save with a backup, the replaced save is backed up itself.
+    /// The backup can be referenced with or without its file extension.
+    pub fn restore_backup(&self, backup_name: &str) -> io::Result<Backup> {
+        let backup = self
+            .list_backups()?
+            .into_iter()
+            .find(|backup| {
+                backup.name == backup_name
+                    || backup.name.strip_suffix(BACKUP_FILE_EXTENSION) == Some(backup_name)
+            })
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("There is no backup named {}", backup_name),
+                )
+            })?;
+
+        let document: Value = serde_json::from_reader(BufReader::new(File::open(&backup.path)?))?;
+        // Only restore backups that can actually be loaded
+        save::from_document(document.clone())?;
+        self.write_document(&document)?;
 
+        Ok(backup)
+    }
+}
+
+/// Writes to a temporary file next to `path`, flushes it to disk and renames
+/// it, so `path` either holds the old or the new contents
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let mut temp_file = File::create(&temp_path)?;
+    temp_file.write_all(contents)?;
+    temp_file.sync_all()?;
+    drop(temp_file);
+
+    fs::rename(&temp_path, path)?;
+
+    // Persist the rename itself
+    #[cfg(unix)]
+    if let Some(directory) = path.parent() {
+        File::open(directory)?.sync_all()?;
+    }
+    Ok(())
+}
+
+fn read_default_profile(default_profile_file_path: &Path) -> io::Result<String> {
+    match fs::read_to_string(default_profile_file_path) {
+        Ok(profile) => Ok(profile.trim().to_string()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(DEFAULT_PROFILE.to_string()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Profile names become file names, so only a safe subset of characters is allowed
+pub fn validate_profile_name(profile: &str) -> io::Result<()> {
+    let is_valid = !profile.is_empty()
+        && profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if is_valid {
         Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Invalid profile name {:?}, only letters, digits, '-' and '_' are allowed",
+                profile
+            ),
+        ))
     }
 }
 
+fn legacy_backup_directory_entries(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for dir_entry in fs::read_dir(directory)? {
+        let path = dir_entry?.path();
+        let is_backup = path.is_file()
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(BACKUP_FILE_PREFIX));
+        if is_backup {
+            entries.push(path);
+        }
+    }
+    Ok(entries)
+}
+
+/// Creates the game directory if needed, the lock file lives in it. Returns
+/// whether the directory was created.
+pub fn create_game_directory(config: &Config) -> io::Result<bool> {
+    if config.game_directory.exists() {
+        return Ok(false);
+    }
+    fs::create_dir_all(&config.game_directory)?;
+    Ok(true)
+}
+
+/// Creates a new game for the profile
 pub fn init_game(config: &Config) -> io::Result<()> {
-    fs::create_dir(&config.game_directory)?;
-
-    println!(
-        "Successfully created a new game directory at {}",
-        config.game_directory.display()
-    );
-    config.save(Game::new())?;
-    println!(
-        "Successfully created a new game file at {}",
-        config.game_file_path.display()
-    );
+    config.adopt_legacy_save()?;
+    if config.profile_exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("The profile {} already exists", config.profile),
+        ));
+    }
+
+    config.save(Game::new(&config.load_catalog()?))
+}
+
+/// Deletes the save and the backups of the profile
+pub fn delete_profile(config: &Config) -> io::Result<()> {
+    if !config.profile_exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("There is no profile named {}", config.profile),
+        ));
+    }
+
+    fs::remove_file(&config.game_file_path)?;
+    if config.backup_directory.exists() {
+        fs::remove_dir_all(&config.backup_directory)?;
+    }
+    if config.default_profile()? == config.profile && config.default_profile_file_path.exists() {
+        fs::remove_file(&config.default_profile_file_path)?;
+    }
     Ok(())
 }
 
 pub fn delete_game_directory(config: &Config) -> io::Result<()> {
-    fs::remove_dir_all(&config.game_directory)?;
+    fs::remove_dir_all(&config.game_directory)
+}
 
-    println!(
-        "Successfully removed all files at {}",
-        config.game_directory.display()
-    );
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, process, thread, time::Duration};
+
+    use super::{Config, DEFAULT_PROFILE, MAX_BACKUPS};
+    use crate::game::{catalog::Catalog, Game};
+
+    fn create_config(test_name: &str) -> Config {
+        let mut home_dir = std::env::temp_dir();
+        home_dir.push(format!("caru-test-{}-{}", process::id(), test_name));
+        let _ = fs::remove_dir_all(&home_dir);
+
+        let config = Config::build(&PathBuf::from(&home_dir), None).unwrap();
+        fs::create_dir_all(&config.game_directory).unwrap();
+        config
+    }
+
+    #[test]
+    fn lock_is_exclusive() {
+        let config = create_config("lock");
+
+        let game_lock = config.lock(Duration::ZERO).unwrap();
+        assert!(config.lock(Duration::from_millis(100)).is_err());
+
+        drop(game_lock);
+        assert!(config.lock(Duration::ZERO).is_ok());
+        fs::remove_dir_all(&config.game_directory).unwrap();
+    }
+
+    #[test]
+    fn legacy_save_becomes_default_profile() {
+        let config = create_config("legacy");
+        let mut legacy_game_file_path = PathBuf::from(&config.game_directory);
+        legacy_game_file_path.push("game.json");
+        fs::write(&legacy_game_file_path, "{}").unwrap();
+
+        config.adopt_legacy_save().unwrap();
+
+        assert_eq!(DEFAULT_PROFILE, config.profile);
+        assert!(!legacy_game_file_path.exists());
+        assert_eq!("{}", fs::read_to_string(&config.game_file_path).unwrap());
+        fs::remove_dir_all(&config.game_directory).unwrap();
+    }
+
+    #[test]
+    fn invalid_profile_names_are_rejected() {
+        let config = create_config("profile-names");
+
+        assert!(config.with_profile("hardcore_2").is_ok());
+        assert!(config.with_profile("../escape").is_err());
+        assert!(config.with_profile("").is_err());
+        fs::remove_dir_all(&config.game_directory).unwrap();
+    }
+
+    #[test]
+    fn save_leaves_no_temporary_file() {
+        let config = create_config("atomic");
+
+        config.save(Game::new(&Catalog::builtin())).unwrap();
+
+        let file_names: Vec<String> = fs::read_dir(&config.game_directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(vec!["profiles".to_string()], file_names);
+        assert!(config.game_file_path.exists());
+        fs::remove_dir_all(&config.game_directory).unwrap();
+    }
+
+    #[test]
+    fn backups_are_limited() {
+        let config = create_config("backup-limit");
+
+        for _ in 0..MAX_BACKUPS + 3 {
+            config.save(Game::new(&Catalog::builtin())).unwrap();
+            thread::sleep(Duration::from_millis(2));
+        }
+
+        assert_eq!(MAX_BACKUPS, config.list_backups().unwrap().len());
+        fs::remove_dir_all(&config.game_directory).unwrap();
+    }
+
+    #[test]
+    fn saves_in_the_same_millisecond_keep_all_backups() {
+        let config = create_config("backup-sequence");
+
+        for _ in 0..4 {
+            config.save(Game::new(&Catalog::builtin())).unwrap();
+        }
+
+        assert_eq!(3, config.list_backups().unwrap().len());
+        fs::remove_dir_all(&config.game_directory).unwrap();
+    }
+
+    #[test]
+    fn restore_replaces_save_with_backup() {
+        let config = create_config("restore");
+        config.save(Game::new(&Catalog::builtin())).unwrap();
+        let first_save = fs::read_to_string(&config.game_file_path).unwrap();
+        thread::sleep(Duration::from_millis(2));
+        config.save(Game::new(&Catalog::builtin())).unwrap();
+        thread::sleep(Duration::from_millis(2));
+
+        let backup = &config.list_backups().unwrap()[0];
+        config.restore_backup(&backup.name).unwrap();
+
+        assert_eq!(
+            first_save,
+            fs::read_to_string(&config.game_file_path).unwrap()
+        );
+        // The replaced save is kept as backup as well
+        assert_eq!(2, config.list_backups().unwrap().len());
+        fs::remove_dir_all(&config.game_directory).unwrap();
+    }
 }
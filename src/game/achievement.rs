@@ -0,0 +1,211 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{game_state::AchievementInformation, Game};
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// A goal of the game, reached once its progress meets the target
+pub struct Achievement {
+    /// Key in the save, never change it
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Additional gold per second once unlocked
+    pub bonus: f64,
+    pub target: f64,
+    pub progress: fn(&Game) -> f64,
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "first_king",
+        name: "Long live the King",
+        description: "Upgrade the King once",
+        bonus: 0.05,
+        target: 1.0,
+        progress: |game| game.entity_level("King") as f64,
+    },
+    Achievement {
+        id: "lumberjack_100",
+        name: "Timber!",
+        description: "Reach 100 Lumberjack levels",
+        bonus: 0.01,
+        target: 100.0,
+        progress: |game| game.entity_level("Lumberjack") as f64,
+    },
+    Achievement {
+        id: "lifetime_gold_1e9",
+        name: "Billionaire",
+        description: "Earn 1e9 Gold in total",
+        bonus: 0.02,
+        target: 1e9,
+        progress: |game| game.lifetime_gold.to_f64(),
+    },
+    Achievement {
+        id: "first_prestige",
+        name: "Born again",
+        description: "Claim a prestige point",
+        bonus: 0.02,
+        target: 1.0,
+        progress: |game| game.prestige_points as f64,
+    },
+    Achievement {
+        id: "days_played_30",
+        name: "Loyal subject",
+        description: "Play for 30 days",
+        bonus: 0.03,
+        target: 30.0,
+        progress: |game| game.time_played().as_secs_f64() / SECONDS_PER_DAY,
+    },
+];
+
+/// An achievement as stored in the save
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnlockedAchievement {
+    pub id: String,
+    pub unlocked_at: SystemTime,
+}
+
+impl Game {
+    /// Unlocks every achievement whose target was reached
+    pub(super) fn check_achievements(&mut self) {
+        let now = self.clock.now();
+        for achievement in ACHIEVEMENTS {
+            if !self.is_unlocked(achievement) && (achievement.progress)(self) >= achievement.target
+            {
+                self.achievements.push(UnlockedAchievement {
+                    id: achievement.id.to_string(),
+                    unlocked_at: now,
+                });
+                self.recent_achievements.push(achievement.name.to_string());
+            }
+        }
+    }
+
+    fn is_unlocked(&self, achievement: &Achievement) -> bool {
+        self.unlocked_at(achievement).is_some()
+    }
+
+    fn unlocked_at(&self, achievement: &Achievement) -> Option<SystemTime> {
+        self.achievements
+            .iter()
+            .find(|unlocked| unlocked.id == achievement.id)
+            .map(|unlocked| unlocked.unlocked_at)
+    }
+
+    /// Gold per second multiplier granted by the unlocked achievements
+    pub(super) fn achievement_multiplier(&self) -> f64 {
+        1.0 + ACHIEVEMENTS
+            .iter()
+            .filter(|achievement| self.is_unlocked(achievement))
+            .map(|achievement| achievement.bonus)
+            .sum::<f64>()
+    }
+
+    pub(super) fn entity_level(&self, entity_name: &str) -> u32 {
+        self.entity_index(entity_name)
+            .map_or(0, |idx| self.idle_entities[idx].get_level())
+    }
+
+    fn time_played(&self) -> Duration {
+        self.clock
+            .now()
+            .duration_since(self.init_time_stamp)
+            .unwrap_or_default()
+    }
+
+    /// Progress of every achievement, unlocked ones first
+    pub fn achievement_infos(&self) -> Vec<AchievementInformation> {
+        let mut infos: Vec<AchievementInformation> = ACHIEVEMENTS
+            .iter()
+            .map(|achievement| {
+                let unlocked_at = self.unlocked_at(achievement);
+                let progress = (achievement.progress)(self);
+                AchievementInformation {
+                    id: achievement.id.to_string(),
+                    name: achievement.name.to_string(),
+                    description: achievement.description.to_string(),
+                    bonus: achievement.bonus,
+                    unlocked_at_unix_seconds: unlocked_at.map(|unlocked_at| {
+                        unlocked_at
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs()
+                    }),
+                    progress: if unlocked_at.is_some() {
+                        1.0
+                    } else {
+                        (progress / achievement.target).clamp(0.0, 1.0)
+                    },
+                    current: progress,
+                    target: achievement.target,
+                }
+            })
+            .collect();
+        infos.sort_by_key(|info| info.unlocked_at_unix_seconds.is_none());
+        infos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, time::Duration};
+
+    use super::ACHIEVEMENTS;
+    use crate::game::{catalog::Catalog, test_support::create_game_with_clock, Game, Gold};
+
+    #[test]
+    fn achievement_ids_are_unique() {
+        let ids: HashSet<&str> = ACHIEVEMENTS
+            .iter()
+            .map(|achievement| achievement.id)
+            .collect();
+
+        assert_eq!(ACHIEVEMENTS.len(), ids.len());
+    }
+
+    #[test]
+    fn upgrade_unlocks_achievement_and_bonus() {
+        let mut game = Game::new(&Catalog::builtin());
+        game.current_gold = Gold::from(1e8);
+
+        game.upgrade("lumberjack", 100);
+
+        assert!(game.achievements.iter().any(|a| a.id == "lumberjack_100"));
+        assert_eq!(vec!["Timber!".to_string()], game.recent_achievements);
+        assert!(game.income_multiplier() > 1.0);
+    }
+
+    #[test]
+    fn update_unlocks_time_based_achievements_once() {
+        let (mut game, clock) = create_game_with_clock();
+
+        clock.advance(Duration::from_secs(30 * 86_400));
+        game.update();
+        game.update();
+
+        let unlocked: Vec<&str> = game.achievements.iter().map(|a| a.id.as_str()).collect();
+        // Only the hours up to the offline cap earn gold, far from a billion
+        assert_eq!(vec!["days_played_30"], unlocked);
+    }
+
+    #[test]
+    fn progress_is_capped() {
+        let mut game = Game::new(&Catalog::builtin());
+        game.current_gold = Gold::from(1e6);
+        game.upgrade("lumberjack", 50);
+
+        let infos = game.achievement_infos();
+
+        let lumberjack = infos
+            .iter()
+            .find(|info| info.id == "lumberjack_100")
+            .unwrap();
+        assert_eq!(0.5, lumberjack.progress);
+        assert!(infos
+            .iter()
+            .all(|info| (0.0..=1.0).contains(&info.progress)));
+    }
+}
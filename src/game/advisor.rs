@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use crate::game::{
+    game_state::{PlannedPurchase, UpgradeAdvice},
+    Game, Gold,
+};
+
+/// Upper bound of purchases in a plan, cheap entities would otherwise be
+/// bought thousands of times within long horizons
+const MAX_PLANNED_PURCHASES: usize = 10_000;
+
+impl Game {
+    /// Ranks every entity by the time its next `levels` levels need to pay
+    /// for themselves, fastest first
+    pub fn advise(&self, levels: u32) -> Vec<UpgradeAdvice> {
+        let total_gold_per_second = self.total_gold_per_second();
+
+        let mut advice: Vec<UpgradeAdvice> = self
+            .idle_entities
+            .iter()
+            .enumerate()
+            .map(|(idx, entity)| {
+                let cost = entity.cost_for_next_upgrades(levels);
+                let gold_per_second_gain = self.gold_per_second_gain(idx, levels);
+
+                UpgradeAdvice {
+                    name: entity.get_name().to_string(),
+                    level: entity.get_level(),
+                    levels,
+                    cost,
+                    gold_per_second_gain,
+                    payback_seconds: cost.ratio(gold_per_second_gain),
+                    seconds_until_affordable: seconds_until_affordable(
+                        cost,
+                        self.current_gold,
+                        total_gold_per_second,
+                    ),
+                }
+            })
+            .collect();
+        advice.sort_by(|a, b| a.payback_seconds.total_cmp(&b.payback_seconds));
+        advice
+    }
+
+    /// Plans which levels to buy within `horizon` if all gold is spent on
+    /// upgrades. Always buys the level that pays for itself the soonest,
+    /// counting the time to save up for it. Consecutive levels of the same
+    /// entity are combined into one purchase.
+    pub fn plan_purchases(&self, horizon: Duration) -> Vec<PlannedPurchase> {
+        let mut game = self.clone();
+        let horizon_seconds = horizon.as_secs_f64();
+        let mut elapsed_seconds = 0.0;
+        let mut plan: Vec<PlannedPurchase> = Vec::new();
+
+        for _ in 0..MAX_PLANNED_PURCHASES {
+            let total_gold_per_second = game.total_gold_per_second();
+            let candidate = (0..game.idle_entities.len())
+                .map(|idx| {
+                    let entity = &game.idle_entities[idx];
+                    let cost = entity.cost_for_next_upgrade();
+                    let wait_seconds =
+                        seconds_until_affordable(cost, game.current_gold, total_gold_per_second);
+                    let payback_seconds = cost.ratio(game.gold_per_second_gain(idx, 1));
+                    (idx, wait_seconds, wait_seconds + payback_seconds)
+                })
+                .filter(|(_, _, score)| score.is_finite())
+                .min_by(|a, b| a.2.total_cmp(&b.2));
+
+            let Some((idx, wait_seconds, _)) = candidate else {
+                break;
+            };
+            if elapsed_seconds + wait_seconds > horizon_seconds {
+                break;
+            }
+
+            elapsed_seconds += wait_seconds;
+            game.current_gold += total_gold_per_second * wait_seconds;
+            let cost = game.idle_entities[idx].cost_for_next_upgrade();
+            // Rounding can leave the gold a tiny bit short of the cost
+            if game.current_gold < cost {
+                game.current_gold = cost;
+            }
+            game.upgrade_entity(idx, 1);
+
+            let entity = &game.idle_entities[idx];
+            let gold_per_second_after = game.total_gold_per_second();
+            match plan.last_mut() {
+                Some(purchase) if purchase.name == entity.get_name() => {
+                    purchase.levels += 1;
+                    purchase.gold_spent += cost;
+                    purchase.gold_per_second_after = gold_per_second_after;
+                }
+                _ => plan.push(PlannedPurchase {
+                    name: entity.get_name().to_string(),
+                    start_seconds: elapsed_seconds,
+                    from_level: entity.get_level() - 1,
+                    levels: 1,
+                    gold_spent: cost,
+                    gold_per_second_after,
+                }),
+            }
+        }
+        plan
+    }
+}
+
+/// Seconds to save up for `cost`, which is infinite without any income
+fn seconds_until_affordable(cost: Gold, gold: Gold, gold_per_second: Gold) -> f64 {
+    if gold >= cost {
+        0.0
+    } else if gold_per_second <= Gold::ZERO {
+        f64::INFINITY
+    } else {
+        (cost - gold).ratio(gold_per_second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game::{catalog::Catalog, Game, Gold};
+
+    fn create_game(gold: f64) -> Game {
+        let mut game = Game::new(&Catalog::builtin());
+        game.current_gold = Gold::from(gold);
+        game
+    }
+
+    #[test]
+    fn advice_is_sorted_by_payback_time() {
+        let game = create_game(0.0);
+
+        let advice = game.advise(1);
+
+        assert_eq!(game.idle_entities.len(), advice.len());
+        assert!(advice
+            .windows(2)
+            .all(|pair| pair[0].payback_seconds <= pair[1].payback_seconds));
+        // 1 gold for more wood for the Bowmaker and the Weaponsmith pays
+        // back within a second, faster than any other
+        assert_eq!("Lumberjack", advice[0].name);
+        assert!(advice[0].payback_seconds < 1.0);
+    }
+
+    #[test]
+    fn starved_consumers_are_not_ranked_above_their_producers() {
+        let game = create_game(0.0);
+
+        let advice = game.advise(1);
+
+        let position = |name: &str| advice.iter().position(|a| a.name == name).unwrap();
+        // The Bowmaker runs at a third of its capacity for lack of wood, more
+        // levels only share the same wood
+        assert!(position("Lumberjack") < position("Bowmaker"));
+        assert_eq!(
+            Gold::ZERO,
+            advice[position("Bowmaker")].gold_per_second_gain
+        );
+    }
+
+    #[test]
+    fn affordable_upgrades_need_no_waiting() {
+        let game = create_game(1_000_000.0);
+
+        let advice = game.advise(1);
+
+        let lumberjack = advice.iter().find(|a| a.name == "Lumberjack").unwrap();
+        let king = advice.iter().find(|a| a.name == "King").unwrap();
+        assert_eq!(0.0, lumberjack.seconds_until_affordable);
+        assert!(king.seconds_until_affordable > 0.0);
+    }
+
+    #[test]
+    fn plan_stays_within_horizon() {
+        let game = create_game(0.0);
+        let horizon = Duration::from_secs(3_600);
+
+        let plan = game.plan_purchases(horizon);
+
+        assert!(!plan.is_empty());
+        assert!(plan
+            .iter()
+            .all(|purchase| purchase.start_seconds <= horizon.as_secs_f64()));
+        assert!(plan
+            .windows(2)
+            .all(|pair| pair[0].start_seconds <= pair[1].start_seconds
+                && pair[0].name != pair[1].name));
+    }
+
+    #[test]
+    fn plan_does_not_change_the_game() {
+        let game = create_game(100.0);
+
+        game.plan_purchases(Duration::from_secs(600));
+
+        assert_eq!(Gold::from(100.0), game.current_gold);
+        assert!(game
+            .idle_entities
+            .iter()
+            .all(|entity| entity.get_level() == 0));
+    }
+}
@@ -0,0 +1,316 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{
+    game_state::{CampaignInformation, CampaignOutcome, LevelLoss, RegionInformation},
+    random::{self, Random},
+    Game, Gold,
+};
+
+/// Army strength every level of the military entities adds
+const ARMY: &[(&str, f64)] = &[("Bowmaker", 1.0), ("Weaponsmith", 4.0), ("Catapult", 20.0)];
+
+/// Share of the levels of the military entities lost in a defeat
+const DEFEAT_LOSS: f64 = 0.1;
+
+/// A region to conquer, ordered by difficulty
+pub struct Region {
+    /// Key in the save, never change it
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Army strength with even odds of winning
+    pub difficulty: f64,
+    pub duration: Duration,
+    pub gold_reward: f64,
+    /// Additional gold per second once conquered
+    pub territory_bonus: f64,
+}
+
+pub const REGIONS: &[Region] = &[
+    Region {
+        id: "borderlands",
+        name: "Borderlands",
+        difficulty: 10.0,
+        duration: Duration::from_secs(10 * 60),
+        gold_reward: 1e4,
+        territory_bonus: 0.02,
+    },
+    Region {
+        id: "river_valley",
+        name: "River valley",
+        difficulty: 50.0,
+        duration: Duration::from_secs(30 * 60),
+        gold_reward: 1e6,
+        territory_bonus: 0.03,
+    },
+    Region {
+        id: "northern_hills",
+        name: "Northern hills",
+        difficulty: 250.0,
+        duration: Duration::from_secs(60 * 60),
+        gold_reward: 1e8,
+        territory_bonus: 0.05,
+    },
+    Region {
+        id: "coastal_cities",
+        name: "Coastal cities",
+        difficulty: 1_000.0,
+        duration: Duration::from_secs(2 * 60 * 60),
+        gold_reward: 1e10,
+        territory_bonus: 0.08,
+    },
+    Region {
+        id: "mountain_fortress",
+        name: "Mountain fortress",
+        difficulty: 5_000.0,
+        duration: Duration::from_secs(4 * 60 * 60),
+        gold_reward: 1e12,
+        territory_bonus: 0.12,
+    },
+    Region {
+        id: "capital",
+        name: "Capital",
+        difficulty: 25_000.0,
+        duration: Duration::from_secs(8 * 60 * 60),
+        gold_reward: 1e15,
+        territory_bonus: 0.2,
+    },
+];
+
+/// A campaign on its way, as stored in the save
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ActiveCampaign {
+    pub region: String,
+    pub ends_at: SystemTime,
+    /// Army strength when the campaign was started
+    pub strength: f64,
+    /// Decides the outcome, so reloading the save cannot change it
+    pub seed: u64,
+}
+
+fn find_region(id: &str) -> Option<&'static Region> {
+    REGIONS
+        .iter()
+        .find(|region| region.id.eq_ignore_ascii_case(id))
+}
+
+/// Chance to conquer a region, even odds at a strength equal to its difficulty
+fn win_chance(strength: f64, difficulty: f64) -> f64 {
+    let ratio = (strength / difficulty).powi(2);
+    ratio / (1.0 + ratio)
+}
+
+impl Game {
+    pub fn army_strength(&self) -> f64 {
+        ARMY.iter()
+            .map(|(entity, strength)| self.entity_level(entity) as f64 * strength)
+            .sum()
+    }
+
+    fn is_conquered(&self, region: &Region) -> bool {
+        self.conquered_regions.iter().any(|id| id == region.id)
+    }
+
+    /// Sends the army to a region, regions have to be conquered in order
+    pub fn start_campaign(&mut self, id: &str) -> Result<CampaignInformation, String> {
+        let region = find_region(id).ok_or_else(|| format!("Unknown region {}", id))?;
+        if let Some(campaign) = self.campaign_info() {
+            return Err(format!(
+                "The army is still on its campaign against {}",
+                campaign.region
+            ));
+        }
+        if self.is_conquered(region) {
+            return Err(format!("{} is already conquered", region.name));
+        }
+        if let Some(previous) = REGIONS
+            .iter()
+            .take_while(|other| other.id != region.id)
+            .find(|other| !self.is_conquered(other))
+        {
+            return Err(format!("{} has to be conquered first", previous.name));
+        }
+        let strength = self.army_strength();
+        if strength <= 0.0 {
+            return Err("There is no army, upgrade Bowmakers, Weaponsmiths or Catapults".into());
+        }
+
+        let now = self.clock.now();
+        self.campaign = Some(ActiveCampaign {
+            region: region.id.to_string(),
+            ends_at: now + region.duration,
+            strength,
+            seed: random::seed_from_time(now),
+        });
+        Ok(self.campaign_info().expect("The campaign was just started"))
+    }
+
+    /// Decides the campaign once its time is up
+    pub(super) fn check_campaign(&mut self) {
+        let Some(campaign) = &self.campaign else {
+            return;
+        };
+        if self.clock.now() < campaign.ends_at {
+            return;
+        }
+        let campaign = self.campaign.take().expect("The campaign exists");
+        let Some(region) = find_region(&campaign.region) else {
+            return;
+        };
+
+        let chance = win_chance(campaign.strength, region.difficulty);
+        let victory = Random::new(campaign.seed).next_f64() < chance;
+        let mut outcome = CampaignOutcome {
+            region: region.name.to_string(),
+            victory,
+            win_chance: chance,
+            gold_won: Gold::ZERO,
+            losses: Vec::new(),
+        };
+        if victory {
+            outcome.gold_won = Gold::from(region.gold_reward);
+            self.current_gold += outcome.gold_won;
+            self.lifetime_gold += outcome.gold_won;
+            self.conquered_regions.push(region.id.to_string());
+        } else {
+            for (entity, _) in ARMY {
+                let Some(idx) = self.entity_index(entity) else {
+                    continue;
+                };
+                let level = self.idle_entities[idx].get_level();
+                let levels = (level as f64 * DEFEAT_LOSS).ceil() as u32;
+                if levels > 0 {
+                    self.idle_entities[idx].remove_levels(levels);
+                    outcome.losses.push(LevelLoss {
+                        entity: entity.to_string(),
+                        levels,
+                    });
+                }
+            }
+        }
+        self.campaign_outcome = Some(outcome);
+    }
+
+    /// Gold per second multiplier granted by the conquered regions
+    pub(super) fn territory_multiplier(&self) -> f64 {
+        1.0 + REGIONS
+            .iter()
+            .filter(|region| self.is_conquered(region))
+            .map(|region| region.territory_bonus)
+            .sum::<f64>()
+    }
+
+    pub(super) fn campaign_info(&self) -> Option<CampaignInformation> {
+        let campaign = self.campaign.as_ref()?;
+        let region = find_region(&campaign.region)?;
+        Some(CampaignInformation {
+            region: region.name.to_string(),
+            strength: campaign.strength,
+            win_chance: win_chance(campaign.strength, region.difficulty),
+            seconds_remaining: campaign
+                .ends_at
+                .duration_since(self.clock.now())
+                .unwrap_or_default()
+                .as_secs(),
+        })
+    }
+
+    /// All regions with the chance the current army has against them
+    pub fn region_infos(&self) -> Vec<RegionInformation> {
+        let strength = self.army_strength();
+        REGIONS
+            .iter()
+            .map(|region| RegionInformation {
+                id: region.id.to_string(),
+                name: region.name.to_string(),
+                difficulty: region.difficulty,
+                duration_seconds: region.duration.as_secs(),
+                gold_reward: Gold::from(region.gold_reward),
+                territory_bonus: region.territory_bonus,
+                conquered: self.is_conquered(region),
+                win_chance: win_chance(strength, region.difficulty),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{win_chance, Random, REGIONS};
+    use crate::game::{test_support::create_game_with_clock, Gold};
+
+    #[test]
+    fn regions_get_harder() {
+        assert!(REGIONS
+            .windows(2)
+            .all(|pair| pair[0].difficulty < pair[1].difficulty));
+        assert_eq!(0.5, win_chance(10.0, 10.0));
+    }
+
+    #[test]
+    fn regions_are_conquered_in_order() {
+        let (mut game, _) = create_game_with_clock();
+        game.current_gold = Gold::from(1e6);
+        game.upgrade("bowmaker", 5);
+
+        assert!(game.start_campaign("river_valley").is_err());
+        assert!(game.start_campaign("borderlands").is_ok());
+        // Only one campaign at a time
+        assert!(game.start_campaign("borderlands").is_err());
+    }
+
+    #[test]
+    fn campaign_is_decided_by_its_seed_after_its_duration() {
+        let (mut game, clock) = create_game_with_clock();
+        game.current_gold = Gold::from(1e6);
+        game.upgrade("bowmaker", 5);
+        game.start_campaign("borderlands").unwrap();
+        let mut other_game = game.clone();
+
+        clock.advance(Duration::from_secs(9 * 60));
+        game.update();
+        assert!(game.campaign.is_some());
+        assert!(game.campaign_outcome.is_none());
+
+        clock.advance(Duration::from_secs(60));
+        game.update();
+        other_game.update();
+
+        let outcome = game.campaign_outcome.as_ref().unwrap();
+        assert!(game.campaign.is_none());
+        assert_eq!(
+            outcome.victory,
+            other_game.campaign_outcome.as_ref().unwrap().victory
+        );
+        if outcome.victory {
+            assert_eq!(vec!["borderlands".to_string()], game.conquered_regions);
+            assert!(game.territory_multiplier() > 1.0);
+        } else {
+            assert_eq!(4, game.entity_level("Bowmaker"));
+        }
+    }
+
+    #[test]
+    fn defeat_costs_army_levels() {
+        let (mut game, clock) = create_game_with_clock();
+        game.current_gold = Gold::from(1e6);
+        game.upgrade("bowmaker", 5);
+        game.start_campaign("borderlands").unwrap();
+        // Strength 5 against 10 wins with a chance of 20%
+        game.campaign.as_mut().unwrap().seed = (0..)
+            .find(|seed| Random::new(*seed).next_f64() > 0.2)
+            .unwrap();
+
+        clock.advance(Duration::from_secs(600));
+        game.update();
+
+        let outcome = game.campaign_outcome.as_ref().unwrap();
+        assert!(!outcome.victory);
+        assert_eq!(1, outcome.losses[0].levels);
+        assert_eq!(4, game.entity_level("Bowmaker"));
+        assert!(game.conquered_regions.is_empty());
+    }
+}
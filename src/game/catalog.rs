@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::Gold;
+
+const BUILTIN_CATALOG: &str = include_str!("../../assets/catalog.json");
+
+/// Definition of all idle entities that can be part of a game
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Catalog {
+    #[serde(default)]
+    offline: OfflineSettings,
+    /// Percentage of the upgrade cost refunded for sold levels
+    #[serde(default = "default_sell_refund_percent")]
+    sell_refund_percent: f64,
+    entities: Vec<CatalogEntry>,
+}
+
+fn default_sell_refund_percent() -> f64 {
+    50.0
+}
+
+/// How much of the time since the last update is credited
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct OfflineSettings {
+    /// Hours after which nothing is earned anymore, before research and
+    /// prestige raise it
+    pub cap_hours: f64,
+    /// Hours credited in full
+    pub full_rate_hours: f64,
+    /// Hours after the full rate at which only half of the time is credited
+    pub falloff_hours: f64,
+}
+
+impl Default for OfflineSettings {
+    fn default() -> Self {
+        OfflineSettings {
+            cap_hours: 12.0,
+            full_rate_hours: 4.0,
+            falloff_hours: 4.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CatalogEntry {
+    pub name: String,
+    /// Gold per second at level 0. Goods are sold as soon as they are made,
+    /// so for a consumer this is the price of its goods at full capacity.
+    pub base_gold_per_second: Gold,
+    pub base_upgrade_cost: Gold,
+    pub upgrade_cost_factor: f64,
+    /// Position of the entity in the progression, entities are ordered by it
+    pub unlock_order: u32,
+    /// Levels that multiply the gold per second, ordered by level
+    #[serde(default)]
+    pub milestones: Vec<Milestone>,
+    /// Resource made by the entity in addition to its gold
+    #[serde(default)]
+    pub produces: Option<ResourceAmount>,
+    /// Resources needed to run the entity at full capacity
+    #[serde(default)]
+    pub consumes: Vec<ResourceAmount>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Milestone {
+    pub level: u32,
+    /// Factor applied to the gold per second from this level on
+    pub multiplier: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ResourceAmount {
+    pub resource: String,
+    /// Units per second at level 0, scaled like the gold per second
+    pub amount: f64,
+}
+
+impl Catalog {
+    /// The catalog shipped with the binary
+    pub fn builtin() -> Self {
+        Catalog::from_json(BUILTIN_CATALOG).expect("The built-in catalog is valid")
+    }
+
+    /// Parses and validates a catalog, entries are sorted by their unlock order
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let mut catalog: Catalog = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        catalog.validate()?;
+        catalog.entities.sort_by_key(|entry| entry.unlock_order);
+        for entry in &mut catalog.entities {
+            entry.milestones.sort_by_key(|milestone| milestone.level);
+        }
+        Ok(catalog)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.entities.is_empty() {
+            return Err("The catalog does not contain any entities".to_string());
+        }
+        let offline = &self.offline;
+        let hours = [
+            offline.cap_hours,
+            offline.full_rate_hours,
+            offline.falloff_hours,
+        ];
+        if hours.iter().any(|hours| !hours.is_finite())
+            || offline.cap_hours <= 0.0
+            || offline.full_rate_hours < 0.0
+            || offline.falloff_hours <= 0.0
+        {
+            return Err(
+                "The offline settings need a positive cap and falloff and a full rate of at least 0 hours"
+                    .to_string(),
+            );
+        }
+
+        if !(0.0..=100.0).contains(&self.sell_refund_percent) {
+            return Err("The sell refund has to be between 0% and 100%".to_string());
+        }
+
+        for (idx, entry) in self.entities.iter().enumerate() {
+            if entry.name.is_empty() {
+                return Err(format!("Entity #{} has no name", idx));
+            }
+            if entry.name.eq_ignore_ascii_case("all") {
+                return Err("\"all\" is reserved and cannot be used as entity name".to_string());
+            }
+            if self.entities[..idx]
+                .iter()
+                .any(|other| other.name.eq_ignore_ascii_case(&entry.name))
+            {
+                return Err(format!("Entity {} is defined more than once", entry.name));
+            }
+            if entry.base_upgrade_cost <= Gold::ZERO || entry.upgrade_cost_factor <= 1.0 {
+                return Err(format!(
+                    "Entity {} needs a positive base upgrade cost and a cost factor above 1",
+                    entry.name
+                ));
+            }
+            for milestone in &entry.milestones {
+                if milestone.level == 0
+                    || milestone.multiplier < 1.0
+                    || !milestone.multiplier.is_finite()
+                {
+                    return Err(format!(
+                        "Milestone {} of entity {} needs a level above 0 and a finite multiplier of at least 1",
+                        milestone.level, entry.name
+                    ));
+                }
+            }
+            if entry
+                .milestones
+                .iter()
+                .enumerate()
+                .any(|(milestone_idx, milestone)| {
+                    entry.milestones[..milestone_idx]
+                        .iter()
+                        .any(|other| other.level == milestone.level)
+                })
+            {
+                return Err(format!(
+                    "Entity {} has more than one milestone at the same level",
+                    entry.name
+                ));
+            }
+            for resource_amount in entry.produces.iter().chain(&entry.consumes) {
+                if resource_amount.resource.is_empty()
+                    || resource_amount.amount <= 0.0
+                    || !resource_amount.amount.is_finite()
+                {
+                    return Err(format!(
+                        "Resources of entity {} need a name and a positive amount",
+                        entry.name
+                    ));
+                }
+            }
+            for consumed in &entry.consumes {
+                if !self.entities.iter().any(|producer| {
+                    producer
+                        .produces
+                        .as_ref()
+                        .is_some_and(|produced| produced.resource == consumed.resource)
+                }) {
+                    return Err(format!(
+                        "Entity {} consumes {} which no entity produces",
+                        entry.name, consumed.resource
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entities
+    }
+
+    pub fn offline_settings(&self) -> &OfflineSettings {
+        &self.offline
+    }
+
+    /// Share of the upgrade cost refunded for sold levels
+    pub fn sell_refund_share(&self) -> f64 {
+        self.sell_refund_percent / 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Catalog;
+
+    #[test]
+    fn builtin_catalog_is_ordered_by_unlock_order() {
+        let catalog = Catalog::builtin();
+
+        assert!(catalog
+            .entries()
+            .windows(2)
+            .all(|pair| pair[0].unlock_order <= pair[1].unlock_order));
+        assert_eq!("Lumberjack", catalog.entries()[0].name);
+    }
+
+    #[test]
+    fn catalog_with_duplicate_names_is_rejected() {
+        let json = r#"{"entities": [
+            {"name": "Miner", "base_gold_per_second": 1.0, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.1, "unlock_order": 0},
+            {"name": "miner", "base_gold_per_second": 2.0, "base_upgrade_cost": 5.0, "upgrade_cost_factor": 1.2, "unlock_order": 1}
+        ]}"#;
+
+        assert!(Catalog::from_json(json).is_err());
+    }
+
+    #[test]
+    fn catalog_with_cost_factor_of_1_is_rejected() {
+        let json = r#"{"entities": [
+            {"name": "Miner", "base_gold_per_second": 1.0, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.0, "unlock_order": 0}
+        ]}"#;
+
+        assert!(Catalog::from_json(json).is_err());
+    }
+
+    #[test]
+    fn milestones_are_sorted_and_validated() {
+        let json = r#"{"entities": [
+            {"name": "Miner", "base_gold_per_second": 1.0, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.1, "unlock_order": 0,
+             "milestones": [{"level": 50, "multiplier": 3.0}, {"level": 25, "multiplier": 2.0}]}
+        ]}"#;
+        let catalog = Catalog::from_json(json).unwrap();
+
+        let levels: Vec<u32> = catalog.entries()[0]
+            .milestones
+            .iter()
+            .map(|milestone| milestone.level)
+            .collect();
+        assert_eq!(vec![25, 50], levels);
+
+        let invalid = json.replace("3.0", "0.5");
+        assert!(Catalog::from_json(&invalid).is_err());
+    }
+
+    #[test]
+    fn offline_settings_default_and_are_validated() {
+        let json = r#"{"entities": [
+            {"name": "Miner", "base_gold_per_second": 1.0, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.1, "unlock_order": 0}
+        ]}"#;
+        let catalog = Catalog::from_json(json).unwrap();
+        assert_eq!(12.0, catalog.offline_settings().cap_hours);
+
+        let invalid = json.replace(
+            r#"{"entities""#,
+            r#"{"offline": {"cap_hours": 0.0}, "entities""#,
+        );
+        assert!(Catalog::from_json(&invalid).is_err());
+    }
+
+    #[test]
+    fn consumed_resources_need_a_producer() {
+        let json = r#"{"entities": [
+            {"name": "Miner", "base_gold_per_second": 1.0, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.1, "unlock_order": 0,
+             "produces": {"resource": "ore", "amount": 1.0}},
+            {"name": "Smith", "base_gold_per_second": 5.0, "base_upgrade_cost": 10.0, "upgrade_cost_factor": 1.2, "unlock_order": 1,
+             "consumes": [{"resource": "ore", "amount": 2.0}]}
+        ]}"#;
+
+        assert!(Catalog::from_json(json).is_ok());
+        let invalid = json.replace(r#"[{"resource": "ore""#, r#"[{"resource": "coal""#);
+        assert!(Catalog::from_json(&invalid).is_err());
+    }
+}
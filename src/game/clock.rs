@@ -0,0 +1,50 @@
+use std::{
+    cell::Cell,
+    fmt::Debug,
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+/// Source of the current time for the game
+pub trait Clock: Debug {
+    fn now(&self) -> SystemTime;
+}
+
+/// The time of the operating system
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to, for tests and simulations
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Cell<SystemTime>,
+}
+
+impl ManualClock {
+    pub fn new(start: SystemTime) -> Self {
+        ManualClock {
+            now: Cell::new(start),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        self.now.get()
+    }
+}
+
+/// Clock of games that are created or loaded without a specific clock
+pub fn system_clock() -> Rc<dyn Clock> {
+    Rc::new(SystemClock)
+}
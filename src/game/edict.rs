@@ -0,0 +1,378 @@
+use std::time::Duration;
+
+use crate::game::{
+    game_state::{EdictInformation, EdictOverview},
+    Game,
+};
+
+/// King levels that unlock another edict slot
+const EDICT_SLOT_LEVELS: &[u32] = &[1, 10, 25];
+
+/// Time between two changes of the edicts
+const EDICT_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+
+/// A policy of the King, active while it occupies a slot
+pub struct Edict {
+    /// Key in the save, never change it
+    pub id: &'static str,
+    pub name: &'static str,
+    pub effects: &'static [EdictEffect],
+}
+
+pub enum EdictEffect {
+    /// Multiplies the gold per second and the produced resources of one
+    /// entity
+    EntityOutput {
+        entity: &'static str,
+        multiplier: f64,
+    },
+    /// Multiplies the gold per second of every entity
+    Income(f64),
+    /// Multiplies the upgrade costs of every entity
+    UpgradeCost(f64),
+}
+
+impl EdictEffect {
+    fn describe(&self) -> String {
+        let percent = |multiplier: f64| format!("{:+.0}%", (multiplier - 1.0) * 100.0);
+        match self {
+            EdictEffect::EntityOutput { entity, multiplier } => {
+                format!("{} {} output", percent(*multiplier), entity)
+            }
+            EdictEffect::Income(multiplier) => format!("{} income", percent(*multiplier)),
+            EdictEffect::UpgradeCost(multiplier) => {
+                format!("{} upgrade costs", percent(*multiplier))
+            }
+        }
+    }
+}
+
+pub const EDICTS: &[Edict] = &[
+    Edict {
+        id: "forestry_act",
+        name: "Forestry act",
+        effects: &[
+            EdictEffect::EntityOutput {
+                entity: "Lumberjack",
+                multiplier: 1.5,
+            },
+            EdictEffect::EntityOutput {
+                entity: "Catapult",
+                multiplier: 0.8,
+            },
+        ],
+    },
+    Edict {
+        id: "builders_charter",
+        name: "Builders' charter",
+        effects: &[EdictEffect::UpgradeCost(0.8), EdictEffect::Income(0.9)],
+    },
+    Edict {
+        id: "taxation",
+        name: "Taxation",
+        effects: &[EdictEffect::Income(1.25), EdictEffect::UpgradeCost(1.15)],
+    },
+    Edict {
+        id: "war_economy",
+        name: "War economy",
+        effects: &[
+            EdictEffect::EntityOutput {
+                entity: "Bowmaker",
+                multiplier: 1.3,
+            },
+            EdictEffect::EntityOutput {
+                entity: "Weaponsmith",
+                multiplier: 1.3,
+            },
+            EdictEffect::EntityOutput {
+                entity: "Stonemason",
+                multiplier: 0.8,
+            },
+        ],
+    },
+    Edict {
+        id: "royal_academy",
+        name: "Royal academy",
+        effects: &[
+            EdictEffect::EntityOutput {
+                entity: "Academic",
+                multiplier: 2.0,
+            },
+            EdictEffect::EntityOutput {
+                entity: "King",
+                multiplier: 0.8,
+            },
+        ],
+    },
+];
+
+fn find_edict(id: &str) -> Option<&'static Edict> {
+    EDICTS
+        .iter()
+        .find(|edict| edict.id.eq_ignore_ascii_case(id))
+}
+
+impl Game {
+    /// Edict slots unlocked by the level of the King
+    fn edict_slots(&self) -> usize {
+        let level = self.entity_level("King");
+        EDICT_SLOT_LEVELS
+            .iter()
+            .filter(|slot_level| level >= **slot_level)
+            .count()
+    }
+
+    /// Edicts in unlocked slots, edicts beyond them wait for the King to level
+    fn active_edicts(&self) -> impl Iterator<Item = &'static Edict> + '_ {
+        self.edicts
+            .iter()
+            .take(self.edict_slots())
+            .filter_map(|id| find_edict(id))
+    }
+
+    fn edict_cooldown(&self) -> Duration {
+        self.edicts_changed_at.map_or(Duration::ZERO, |changed_at| {
+            EDICT_COOLDOWN.saturating_sub(
+                self.clock
+                    .now()
+                    .duration_since(changed_at)
+                    .unwrap_or_default(),
+            )
+        })
+    }
+
+    fn check_edict_cooldown(&self) -> Result<(), String> {
+        let cooldown = self.edict_cooldown();
+        if cooldown > Duration::ZERO {
+            return Err(format!(
+                "The edicts were changed recently, wait {} more seconds",
+                cooldown.as_secs()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Proclaims an edict in the given slot (starting at 1) or the first free
+    /// slot, replacing the edict in that slot
+    pub fn set_edict(&mut self, id: &str, slot: Option<usize>) -> Result<(), String> {
+        let edict = find_edict(id).ok_or_else(|| format!("Unknown edict {}", id))?;
+        let slots = self.edict_slots();
+        if slots == 0 {
+            return Err(format!(
+                "The King needs level {} for the first edict",
+                EDICT_SLOT_LEVELS[0]
+            ));
+        }
+        if self.edicts.iter().any(|active| active == edict.id) {
+            return Err(format!("{} is already proclaimed", edict.name));
+        }
+        if let Some(slot) = slot {
+            if slot == 0 || slot > slots {
+                return Err(format!("There are only {} edict slots", slots));
+            }
+        }
+        if slot.is_none() && self.edicts.len() >= slots {
+            return Err(format!(
+                "All {} edict slots are taken, choose a slot to replace",
+                slots
+            ));
+        }
+        self.check_edict_cooldown()?;
+
+        // Edicts beyond the unlocked slots are kept, they wait for the King
+        match slot {
+            Some(slot) if slot <= self.edicts.len() => {
+                self.edicts[slot - 1] = edict.id.to_string();
+            }
+            _ => self.edicts.push(edict.id.to_string()),
+        }
+        self.edicts_changed_at = Some(self.clock.now());
+        self.apply_edicts();
+        Ok(())
+    }
+
+    /// Revokes an edict
+    pub fn clear_edict(&mut self, id: &str) -> Result<(), String> {
+        let idx = self
+            .edicts
+            .iter()
+            .position(|active| active.eq_ignore_ascii_case(id))
+            .ok_or_else(|| format!("{} is not proclaimed", id))?;
+        self.check_edict_cooldown()?;
+
+        self.edicts.remove(idx);
+        self.edicts_changed_at = Some(self.clock.now());
+        self.apply_edicts();
+        Ok(())
+    }
+
+    /// Gold per second multiplier of the edicts for an entity
+    pub(super) fn edict_output_multiplier(&self, entity_name: &str) -> f64 {
+        self.active_edicts()
+            .flat_map(|edict| edict.effects)
+            .map(|effect| match effect {
+                EdictEffect::EntityOutput { entity, multiplier } if *entity == entity_name => {
+                    *multiplier
+                }
+                EdictEffect::Income(multiplier) => *multiplier,
+                _ => 1.0,
+            })
+            .product()
+    }
+
+    /// Multiplier of the edicts for the resources an entity produces, only
+    /// its output edicts count, the income edicts are about gold
+    pub(super) fn edict_production_multiplier(&self, entity_name: &str) -> f64 {
+        self.active_edicts()
+            .flat_map(|edict| edict.effects)
+            .map(|effect| match effect {
+                EdictEffect::EntityOutput { entity, multiplier } if *entity == entity_name => {
+                    *multiplier
+                }
+                _ => 1.0,
+            })
+            .product()
+    }
+
+    /// Hands the upgrade cost multiplier of the edicts to the entities. Has
+    /// to be called whenever the edicts or the level of the King change.
+    pub(super) fn apply_edicts(&mut self) {
+        let cost_multiplier: f64 = self
+            .active_edicts()
+            .flat_map(|edict| edict.effects)
+            .map(|effect| match effect {
+                EdictEffect::UpgradeCost(multiplier) => *multiplier,
+                _ => 1.0,
+            })
+            .product();
+        for entity in &mut self.idle_entities {
+            entity.set_cost_multiplier(cost_multiplier);
+        }
+    }
+
+    /// Names of the edicts in effect
+    pub(super) fn active_edict_names(&self) -> Vec<String> {
+        self.active_edicts()
+            .map(|edict| edict.name.to_string())
+            .collect()
+    }
+
+    pub fn edict_overview(&self) -> EdictOverview {
+        let level = self.entity_level("King");
+        EdictOverview {
+            slots: self.edict_slots(),
+            next_slot_level: EDICT_SLOT_LEVELS
+                .iter()
+                .copied()
+                .find(|slot_level| *slot_level > level),
+            cooldown_seconds: self.edict_cooldown().as_secs(),
+            edicts: EDICTS
+                .iter()
+                .map(|edict| EdictInformation {
+                    id: edict.id.to_string(),
+                    name: edict.name.to_string(),
+                    description: edict
+                        .effects
+                        .iter()
+                        .map(EdictEffect::describe)
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                    slot: self
+                        .edicts
+                        .iter()
+                        .position(|active| active == edict.id)
+                        .map(|idx| idx + 1),
+                    active: self.active_edicts().any(|active| active.id == edict.id),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game::{test_support::create_game_with_clock, Game, Gold};
+
+    fn crown_king(game: &mut Game, king_level: u32) {
+        game.current_gold = Gold::from(1e100);
+        game.upgrade("king", king_level);
+        game.current_gold = Gold::ZERO;
+    }
+
+    #[test]
+    fn king_level_unlocks_slots() {
+        let (mut game, _) = create_game_with_clock();
+        assert!(game.set_edict("taxation", None).is_err());
+
+        let (mut game, _) = create_game_with_clock();
+        crown_king(&mut game, 10);
+        assert_eq!(2, game.edict_slots());
+        assert!(game.set_edict("taxation", None).is_ok());
+        assert_eq!(Some(25), game.edict_overview().next_slot_level);
+    }
+
+    #[test]
+    fn edicts_change_income_and_costs() {
+        let (mut game, clock) = create_game_with_clock();
+        crown_king(&mut game, 1);
+        let lumberjack_gold_per_second = game.entity_gold_per_second(0, 1.0);
+        let lumberjack_cost = game.idle_entities[0].cost_for_next_upgrade();
+
+        let wood_per_second = game.resource_infos()[0].production_per_second;
+
+        game.set_edict("forestry_act", None).unwrap();
+        assert_eq!(
+            lumberjack_gold_per_second * 1.5,
+            game.entity_gold_per_second(0, 1.0)
+        );
+        // The Lumberjack cuts more wood as well
+        assert_eq!(
+            wood_per_second * 1.5,
+            game.resource_infos()[0].production_per_second
+        );
+
+        clock.advance(Duration::from_secs(3_600));
+        game.set_edict("taxation", Some(1)).unwrap();
+        assert_eq!(
+            lumberjack_cost * 1.15,
+            game.idle_entities[0].cost_for_next_upgrade()
+        );
+        assert_eq!(vec!["Taxation".to_string()], game.active_edict_names());
+    }
+
+    #[test]
+    fn edicts_beyond_the_slots_wait_for_the_king() {
+        let (mut game, clock) = create_game_with_clock();
+        crown_king(&mut game, 10);
+        game.set_edict("taxation", None).unwrap();
+        clock.advance(Duration::from_secs(3_600));
+        game.set_edict("forestry_act", None).unwrap();
+        game.sell("king", 9).unwrap();
+        clock.advance(Duration::from_secs(3_600));
+
+        assert!(game.set_edict("royal_academy", Some(2)).is_err());
+        game.set_edict("royal_academy", Some(1)).unwrap();
+        assert_eq!(vec!["Royal academy".to_string()], game.active_edict_names());
+
+        game.current_gold = Gold::from(1e100);
+        game.upgrade("king", 9);
+        assert_eq!(
+            vec!["Royal academy".to_string(), "Forestry act".to_string()],
+            game.active_edict_names()
+        );
+    }
+
+    #[test]
+    fn changes_wait_for_the_cooldown() {
+        let (mut game, clock) = create_game_with_clock();
+        crown_king(&mut game, 1);
+        game.set_edict("taxation", None).unwrap();
+
+        assert!(game.clear_edict("taxation").is_err());
+        clock.advance(Duration::from_secs(3_600));
+        assert!(game.clear_edict("taxation").is_ok());
+        assert!(game.active_edict_names().is_empty());
+    }
+}
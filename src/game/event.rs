@@ -0,0 +1,349 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{
+    game_state::{ActiveEventInformation, EventInformation},
+    random::{self, Random},
+    Game, Gold,
+};
+
+/// Average time between two events
+const MEAN_EVENT_GAP: Duration = Duration::from_secs(30 * 60);
+
+/// Number of events kept in the log of the save
+const EVENT_LOG_LENGTH: usize = 50;
+
+/// Something that happens to the kingdom at a random time
+pub struct RandomEvent {
+    /// Key in the save, never change it
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Relative chance of the event compared to the other events
+    pub weight: f64,
+    pub effect: EventEffect,
+}
+
+pub enum EventEffect {
+    /// Multiplies the gold per second of an entity for a while
+    Boost {
+        entity: &'static str,
+        multiplier: f64,
+        duration: Duration,
+    },
+    /// Takes a share of the current gold
+    Theft(f64),
+    /// Pays the gold per second of this many seconds
+    Trade(f64),
+}
+
+pub const EVENTS: &[RandomEvent] = &[
+    RandomEvent {
+        id: "bountiful_harvest",
+        name: "Bountiful harvest",
+        weight: 3.0,
+        effect: EventEffect::Boost {
+            entity: "Lumberjack",
+            multiplier: 3.0,
+            duration: Duration::from_secs(10 * 60),
+        },
+    },
+    RandomEvent {
+        id: "rich_quarry",
+        name: "Rich quarry",
+        weight: 2.0,
+        effect: EventEffect::Boost {
+            entity: "Stonemason",
+            multiplier: 2.0,
+            duration: Duration::from_secs(15 * 60),
+        },
+    },
+    RandomEvent {
+        id: "bandits",
+        name: "Bandits",
+        weight: 2.0,
+        effect: EventEffect::Theft(0.05),
+    },
+    RandomEvent {
+        id: "merchant",
+        name: "Merchant",
+        weight: 3.0,
+        effect: EventEffect::Trade(5.0 * 60.0),
+    },
+];
+
+/// Random events of a game as stored in the save. The generator is stored
+/// with its state, so the same save always rolls the same events.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventState {
+    random: Random,
+    next_event_at: SystemTime,
+    /// Boosts which are still running
+    active: Vec<ActiveEvent>,
+    /// The latest events, oldest first
+    log: Vec<EventLogEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ActiveEvent {
+    id: String,
+    ends_at: SystemTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventLogEntry {
+    pub at: SystemTime,
+    pub name: String,
+    pub description: String,
+}
+
+impl EventState {
+    pub fn new(now: SystemTime) -> Self {
+        let mut random = Random::new(random::seed_from_time(now));
+        let next_event_at = now + random_gap(&mut random);
+        EventState {
+            random,
+            next_event_at,
+            active: Vec::new(),
+            log: Vec::new(),
+        }
+    }
+}
+
+/// Exponentially distributed time until the next event
+fn random_gap(random: &mut Random) -> Duration {
+    let seconds = -(1.0 - random.next_f64()).ln() * MEAN_EVENT_GAP.as_secs_f64();
+    Duration::from_secs_f64(seconds.max(1.0))
+}
+
+fn find_event(id: &str) -> Option<&'static RandomEvent> {
+    EVENTS.iter().find(|event| event.id == id)
+}
+
+impl Game {
+    /// The next time after `time` at which an event starts or ends
+    pub(super) fn next_event_change(&self, time: SystemTime) -> Option<SystemTime> {
+        if self.events_paused {
+            return None;
+        }
+        self.events
+            .active
+            .iter()
+            .map(|active| active.ends_at)
+            .chain([self.events.next_event_at])
+            .filter(|at| *at > time)
+            .min()
+    }
+
+    /// Ends the boosts that ran out and rolls the events due at `time`
+    pub(super) fn trigger_events(&mut self, time: SystemTime) {
+        if self.events_paused {
+            return;
+        }
+        self.events.active.retain(|active| active.ends_at > time);
+
+        while self.events.next_event_at <= time {
+            let total_weight: f64 = EVENTS.iter().map(|event| event.weight).sum();
+            let mut roll = self.events.random.next_f64() * total_weight;
+            let event = EVENTS
+                .iter()
+                .find(|event| {
+                    roll -= event.weight;
+                    roll < 0.0
+                })
+                .unwrap_or(&EVENTS[EVENTS.len() - 1]);
+
+            let description = self.apply_event(event, time);
+            let entry = EventLogEntry {
+                at: time,
+                name: event.name.to_string(),
+                description,
+            };
+            self.events.log.push(entry.clone());
+            self.recent_events.push(entry);
+            self.events.next_event_at += random_gap(&mut self.events.random);
+        }
+
+        let overflow = self.events.log.len().saturating_sub(EVENT_LOG_LENGTH);
+        self.events.log.drain(..overflow);
+    }
+
+    fn apply_event(&mut self, event: &RandomEvent, time: SystemTime) -> String {
+        match event.effect {
+            EventEffect::Boost {
+                entity,
+                multiplier,
+                duration,
+            } => {
+                self.events.active.push(ActiveEvent {
+                    id: event.id.to_string(),
+                    ends_at: time + duration,
+                });
+                format!(
+                    "{} produce x{} for {} minutes",
+                    entity,
+                    multiplier,
+                    duration.as_secs() / 60
+                )
+            }
+            EventEffect::Theft(share) => {
+                let stolen = self.current_gold * share;
+                self.current_gold -= stolen;
+                format!("Stole {:.2} Gold", stolen)
+            }
+            EventEffect::Trade(seconds) => {
+                let earned: Gold = self.total_gold_per_second() * seconds;
+                self.current_gold += earned;
+                self.lifetime_gold += earned;
+                format!("Paid {:.2} Gold for your goods", earned)
+            }
+        }
+    }
+
+    /// Lets the time until `now` pass without any events, e.g. after the
+    /// offline cap
+    pub(super) fn skip_events(&mut self, now: SystemTime) {
+        self.events.active.retain(|active| active.ends_at > now);
+        while self.events.next_event_at <= now {
+            self.events.next_event_at += random_gap(&mut self.events.random);
+        }
+    }
+
+    /// Gold per second multiplier of the running boosts for an entity
+    pub(super) fn event_output_multiplier(&self, entity_name: &str) -> f64 {
+        self.events
+            .active
+            .iter()
+            .filter_map(|active| find_event(&active.id))
+            .map(|event| match event.effect {
+                EventEffect::Boost {
+                    entity, multiplier, ..
+                } if entity == entity_name => multiplier,
+                _ => 1.0,
+            })
+            .product()
+    }
+
+    /// Leaves the events out, e.g. for forecasts
+    pub(super) fn pause_events(&mut self) {
+        self.events_paused = true;
+    }
+
+    pub(super) fn recent_event_infos(&self) -> Vec<EventInformation> {
+        self.recent_events
+            .iter()
+            .map(|entry| EventInformation {
+                at_unix_seconds: entry
+                    .at
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                name: entry.name.clone(),
+                description: entry.description.clone(),
+            })
+            .collect()
+    }
+
+    pub(super) fn active_event_infos(&self) -> Vec<ActiveEventInformation> {
+        let now = self.clock.now();
+        self.events
+            .active
+            .iter()
+            .filter_map(|active| {
+                find_event(&active.id).map(|event| ActiveEventInformation {
+                    name: event.name.to_string(),
+                    seconds_remaining: active
+                        .ends_at
+                        .duration_since(now)
+                        .unwrap_or_default()
+                        .as_secs(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{ActiveEvent, Random};
+    use crate::game::{test_support::create_game_with_clock, Game};
+
+    fn after(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn events_do_not_depend_on_how_often_the_game_is_updated() {
+        let (mut game, clock) = create_game_with_clock();
+        let mut other_game = game.clone();
+
+        // Hourly updates against a single one, which only rolls events
+        // until the offline cap
+        for _ in 0..24 {
+            clock.advance(Duration::from_secs(3_600));
+            game.update();
+        }
+        other_game.update();
+
+        let cap_end = after((other_game.offline_cap_hours() * 3_600.0) as u64);
+        let log = |game: &Game| -> Vec<(SystemTime, String)> {
+            game.events
+                .log
+                .iter()
+                .filter(|entry| entry.at <= cap_end)
+                .map(|entry| (entry.at, entry.name.clone()))
+                .collect()
+        };
+        assert!(!log(&game).is_empty());
+        assert!(game.events.log.iter().any(|entry| entry.at > cap_end));
+        assert_eq!(log(&game), log(&other_game));
+        assert_eq!(other_game.events.log.len(), log(&other_game).len());
+        assert_eq!(game.recent_events.len(), game.events.log.len());
+    }
+
+    #[test]
+    fn bandits_steal_the_gold_earned_before_they_arrive() {
+        let (mut game, clock) = create_game_with_clock();
+        let gold_per_second = game.total_gold_per_second();
+        // Bandits are rolled in [0.5, 0.7), the next event comes much later
+        let seed = (0..)
+            .find(|seed| {
+                let mut random = Random::new(*seed);
+                (0.5..0.7).contains(&random.next_f64()) && random.next_f64() > 0.1
+            })
+            .unwrap();
+        game.events.random = Random::new(seed);
+        game.events.next_event_at = after(10);
+
+        clock.advance(Duration::from_secs(20));
+        let game_state = game.update();
+
+        assert_eq!("Bandits", game_state.events[0].name);
+        assert_eq!(10, game_state.events[0].at_unix_seconds);
+        let expected = gold_per_second * 10.0 * 0.95 + gold_per_second * 10.0;
+        assert!((expected - game_state.game_info.gold).to_f64().abs() < 1e-9);
+    }
+
+    #[test]
+    fn boost_only_applies_while_it_runs() {
+        let (mut game, clock) = create_game_with_clock();
+        game.events.next_event_at = after(1_000);
+        let normal = game.total_gold_per_second();
+        game.events.active.push(ActiveEvent {
+            id: "bountiful_harvest".to_string(),
+            ends_at: after(10),
+        });
+        let boosted = game.total_gold_per_second();
+
+        // 10 boosted seconds followed by 20 normal ones
+        clock.advance(Duration::from_secs(30));
+        let game_state = game.update();
+
+        assert!(boosted > normal);
+        let expected = boosted * 10.0 + normal * 20.0;
+        assert!((expected - game_state.game_info.gold).to_f64().abs() < 1e-9);
+        assert!(game_state.active_events.is_empty());
+    }
+}
@@ -1,28 +1,334 @@
-#[derive(Debug)]
+use serde::Serialize;
+
+use crate::game::Gold;
+
+#[derive(Debug, Serialize)]
 pub struct GameState {
     pub game_info: GameInformation,
-    pub lumberjack_info: IdleEntityInformation,
-    pub stonemason_info: IdleEntityInformation,
-    pub bowmaker_info: IdleEntityInformation,
-    pub weaponsmith_info: IdleEntityInformation,
-    pub academic_info: IdleEntityInformation,
-    pub catapult_info: IdleEntityInformation,
-    pub king_info: IdleEntityInformation,
+    /// Information for every entity, in catalog order
+    pub entity_infos: Vec<IdleEntityInformation>,
+    /// Names of the achievements unlocked since the game was loaded
+    pub new_achievements: Vec<String>,
+    pub resources: Vec<ResourceInformation>,
+    /// The campaign on its way, if any
+    pub campaign: Option<CampaignInformation>,
+    /// Campaign decided since the game was loaded
+    pub campaign_outcome: Option<CampaignOutcome>,
+    /// Events rolled since the game was loaded, oldest first
+    pub events: Vec<EventInformation>,
+    /// Boosts of events which are still running
+    pub active_events: Vec<ActiveEventInformation>,
+    /// Levels the managers bought since the game was loaded, per entity
+    pub manager_purchases: Vec<UpgradeSummary>,
+    /// How much of the time away was credited, once the returns diminish
+    pub offline_progress: Option<OfflineProgress>,
 }
 
-#[derive(Debug)]
+impl GameState {
+    /// Looks up the information of an entity by its name, ignoring case
+    pub fn find_entity_info(&self, name: &str) -> Option<&IdleEntityInformation> {
+        self.entity_infos
+            .iter()
+            .find(|entity_info| entity_info.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct GameInformation {
-    pub gold_per_second: f64,
-    pub gold: f64,
+    pub gold_per_second: Gold,
+    pub gold: Gold,
+    pub lifetime_gold: Gold,
+    pub prestige_points: u64,
+    pub prestige_multiplier: f64,
+    pub achievements_unlocked: usize,
+    pub achievement_multiplier: f64,
+    /// Names of the edicts of the King in effect
+    pub active_edicts: Vec<String>,
+    pub army_strength: f64,
+    pub territory_multiplier: f64,
+    /// Seconds the clock went backwards since the last update, in which case
+    /// no gold was earned
+    pub clock_skew_seconds: Option<f64>,
+    /// Hours after the last update that still earn
+    pub offline_cap_hours: f64,
+    /// Streak the next work continues, 0 once it is broken
+    pub work_streak: u32,
+    /// Seconds until the next work
+    pub work_cooldown_seconds: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct IdleEntityInformation {
     pub name: String,
     pub level: u32,
-    pub gold_per_second: f64,
+    pub gold_per_second: Gold,
     pub gold_per_second_percent: f64,
     pub maximum_upgrade_quantity: u32,
-    pub upgrade_cost_next: f64,
-    pub upgrade_cost_max: f64,
+    pub upgrade_cost_next: Gold,
+    pub upgrade_cost_max: Gold,
+    /// Share of its capacity the entity runs at, below 1 while the resources
+    /// it consumes run short
+    pub throughput: f64,
+    /// Product of the multipliers of the reached milestones
+    pub milestone_multiplier: f64,
+    pub next_milestone: Option<MilestoneInformation>,
+    /// Gold refunded for selling the last level
+    pub sell_refund_next: Gold,
+    /// Gold refunded for selling every level
+    pub sell_refund_all: Gold,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceInformation {
+    pub name: String,
+    pub stockpile: f64,
+    pub production_per_second: f64,
+    /// Units used per second at the current throughput of the consumers
+    pub consumption_per_second: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EdictOverview {
+    /// Slots unlocked by the level of the King
+    pub slots: usize,
+    /// King level that unlocks the next slot
+    pub next_slot_level: Option<u32>,
+    /// Seconds until the edicts can be changed again
+    pub cooldown_seconds: u64,
+    pub edicts: Vec<EdictInformation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EdictInformation {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Slot of a proclaimed edict, starting at 1
+    pub slot: Option<usize>,
+    /// Whether the edict is proclaimed in an unlocked slot
+    pub active: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegionInformation {
+    pub id: String,
+    pub name: String,
+    pub difficulty: f64,
+    pub duration_seconds: u64,
+    pub gold_reward: Gold,
+    pub territory_bonus: f64,
+    pub conquered: bool,
+    /// Chance of the current army to conquer the region
+    pub win_chance: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CampaignInformation {
+    pub region: String,
+    pub strength: f64,
+    pub win_chance: f64,
+    pub seconds_remaining: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CampaignOutcome {
+    pub region: String,
+    pub victory: bool,
+    pub win_chance: f64,
+    pub gold_won: Gold,
+    /// Levels of the military entities lost in a defeat
+    pub losses: Vec<LevelLoss>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LevelLoss {
+    pub entity: String,
+    pub levels: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResearchNodeInformation {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Research points to spend
+    pub cost: f64,
+    /// Ids of the nodes which have to be researched first
+    pub prerequisites: Vec<String>,
+    pub required_levels: Vec<RequiredLevel>,
+    pub researched: bool,
+    /// Prerequisites and entity levels which are not reached yet
+    pub missing_requirements: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequiredLevel {
+    pub entity: String,
+    pub level: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MilestoneInformation {
+    pub level: u32,
+    pub multiplier: f64,
+    pub levels_remaining: u32,
+    /// Cost of all levels up to the milestone
+    pub cost: Gold,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UpgradeSummary {
+    pub name: String,
+    pub levels: u32,
+    pub gold_spent: Gold,
+}
+
+/// How fast the next levels of an entity pay for themselves
+#[derive(Debug, Serialize)]
+pub struct UpgradeAdvice {
+    pub name: String,
+    pub level: u32,
+    /// Number of levels the advice is about
+    pub levels: u32,
+    pub cost: Gold,
+    pub gold_per_second_gain: Gold,
+    /// Seconds until the gained gold covers the cost, infinite without gain
+    pub payback_seconds: f64,
+    /// Seconds to save up for the cost at the current gold per second
+    pub seconds_until_affordable: f64,
+}
+
+/// Consecutive levels of one entity bought as part of a purchase plan
+#[derive(Debug, Serialize)]
+pub struct PlannedPurchase {
+    pub name: String,
+    /// Seconds from now until the first of the levels is bought
+    pub start_seconds: f64,
+    pub from_level: u32,
+    pub levels: u32,
+    pub gold_spent: Gold,
+    pub gold_per_second_after: Gold,
+}
+
+/// Outcome of `Game::simulate`
+#[derive(Debug, Serialize)]
+pub struct SimulationReport {
+    pub duration_seconds: u64,
+    pub gold_earned: Gold,
+    pub gold_spent: Gold,
+    /// Every step in which upgrades were bought
+    pub timeline: Vec<SimulationStep>,
+    pub final_state: GameState,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulationStep {
+    /// Simulated seconds since the start of the simulation
+    pub elapsed_seconds: u64,
+    /// Entities with at least one bought level
+    pub upgrades: Vec<UpgradeSummary>,
+    pub gold_per_second_after: Gold,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AchievementInformation {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Additional gold per second once unlocked
+    pub bonus: f64,
+    pub unlocked_at_unix_seconds: Option<u64>,
+    /// Share of the target reached, in [0, 1]
+    pub progress: f64,
+    pub current: f64,
+    pub target: f64,
+}
+
+/// Outcome of `Game::upgrade`
+#[derive(Debug, Serialize)]
+pub struct UpgradeResult {
+    pub name: String,
+    /// `u32::MAX` when as many upgrades as affordable were requested
+    pub requested: u32,
+    pub successful: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrestigeSummary {
+    pub lifetime_gold: Gold,
+    pub points_before: u64,
+    pub points_gained: u64,
+    pub multiplier_before: f64,
+    pub multiplier_after: f64,
+    /// Lifetime gold needed to gain one more prestige point
+    pub lifetime_gold_for_next_point: Gold,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventInformation {
+    pub at_unix_seconds: u64,
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActiveEventInformation {
+    pub name: String,
+    pub seconds_remaining: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManagerOverview {
+    pub managers: Vec<ManagerInformation>,
+    /// The latest purchases, oldest first
+    pub purchases: Vec<ManagerPurchaseInformation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManagerInformation {
+    pub entity: String,
+    pub unlocked: bool,
+    /// Name of the research node which unlocks the manager
+    pub unlocked_by: String,
+    pub rules: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManagerPurchaseInformation {
+    pub at_unix_seconds: u64,
+    pub entity: String,
+    pub levels: u32,
+    pub gold_spent: Gold,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OfflineProgress {
+    pub away_seconds: f64,
+    pub credited_seconds: f64,
+    pub cap_seconds: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkSummary {
+    /// Whether the cooldown was still running, nothing is paid then
+    pub too_early: bool,
+    pub gold_earned: Gold,
+    pub streak: u32,
+    pub combo_multiplier: f64,
+    /// Seconds until the next work
+    pub cooldown_seconds: u64,
+    /// Seconds the early attempt added to the cooldown
+    pub penalty_seconds: u64,
+    /// Paid works left within the last day
+    pub works_left_today: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SellSummary {
+    pub name: String,
+    /// Levels sold, at most the levels the entity had
+    pub levels: u32,
+    pub refund: Gold,
+    /// Level after the sale
+    pub level: u32,
 }
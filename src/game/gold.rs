@@ -0,0 +1,398 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// 2^256, the base of `Gold::exponent`
+const SCALE: f64 = f64::from_bits((1023 + 256) << 52);
+/// 2^-256
+const INV_SCALE: f64 = f64::from_bits((1023 - 256) << 52);
+const SCALE_LOG2: f64 = 256.0;
+/// Values below this bound are displayed as plain decimals
+const SCIENTIFIC_THRESHOLD: f64 = 1e15;
+
+/// An amount of gold that does not overflow where `f64` does.
+///
+/// The value is `mantissa * 2^(256 * exponent)` with `1 <= |mantissa| < 2^256`.
+/// Scaling by powers of two is exact, so as long as a value fits into an `f64`
+/// all arithmetic yields exactly the same result as the `f64` arithmetic.
+/// Like `f64`, non-finite values propagate instead of panicking.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Gold {
+    mantissa: f64,
+    exponent: i64,
+}
+
+impl Gold {
+    pub const ZERO: Gold = Gold {
+        mantissa: 0.0,
+        exponent: 0,
+    };
+
+    fn normalized(mut mantissa: f64, mut exponent: i64) -> Self {
+        if mantissa == 0.0 {
+            return Gold::ZERO;
+        }
+        if !mantissa.is_finite() {
+            return Gold {
+                mantissa,
+                exponent: 0,
+            };
+        }
+
+        while mantissa.abs() >= SCALE {
+            mantissa *= INV_SCALE;
+            exponent += 1;
+        }
+        while mantissa.abs() < 1.0 {
+            mantissa *= SCALE;
+            exponent -= 1;
+        }
+        Gold { mantissa, exponent }
+    }
+
+    /// `base^exponent`, calculated by repeated squaring
+    pub fn powi(base: f64, exponent: u32) -> Self {
+        let mut result = Gold::from(1.0);
+        let mut square = Gold::from(base);
+        let mut remaining = exponent;
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = result * square;
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                square = square * square;
+            }
+        }
+        result
+    }
+
+    /// Converts to an `f64`, which is infinite if the value is too large
+    pub fn to_f64(self) -> f64 {
+        scale(self.mantissa, self.exponent)
+    }
+
+    /// The natural logarithm
+    pub fn ln(self) -> f64 {
+        self.mantissa.ln() + (self.exponent as f64) * SCALE_LOG2 * std::f64::consts::LN_2
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.mantissa == 0.0
+    }
+
+    pub fn is_finite(self) -> bool {
+        self.mantissa.is_finite()
+    }
+
+    pub fn is_sign_negative(self) -> bool {
+        self.mantissa < 0.0
+    }
+
+    /// `self / other` as a plain ratio
+    pub fn ratio(self, other: Self) -> f64 {
+        (self / other).to_f64()
+    }
+
+    /// Splits the absolute value into a decimal mantissa in `[1, 10)` and a
+    /// decimal exponent
+    fn to_scientific(self) -> (f64, i64) {
+        let log10 = self.mantissa.abs().log10()
+            + (self.exponent as f64) * SCALE_LOG2 * std::f64::consts::LOG10_2;
+        let exponent = log10.floor();
+        let mut mantissa = f64::powf(10.0, log10 - exponent);
+        let mut exponent = exponent as i64;
+        // Rounding of the logarithm can end up just outside of [1, 10)
+        if mantissa >= 10.0 {
+            mantissa /= 10.0;
+            exponent += 1;
+        }
+        (mantissa, exponent)
+    }
+}
+
+/// `mantissa * 2^(256 * exponent)` as an `f64`
+fn scale(mut mantissa: f64, exponent: i64) -> f64 {
+    if exponent > 0 {
+        for _ in 0..exponent.min(5) {
+            mantissa *= SCALE;
+        }
+    } else {
+        for _ in 0..(-exponent).min(5) {
+            mantissa *= INV_SCALE;
+        }
+    }
+    mantissa
+}
+
+impl From<f64> for Gold {
+    fn from(value: f64) -> Self {
+        Gold::normalized(value, 0)
+    }
+}
+
+impl Add for Gold {
+    type Output = Gold;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        if self.is_zero() {
+            return rhs;
+        }
+        if rhs.is_zero() {
+            return self;
+        }
+        if !self.is_finite() || !rhs.is_finite() {
+            return Gold::from(self.to_f64() + rhs.to_f64());
+        }
+
+        let (larger, smaller) = if self.exponent >= rhs.exponent {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
+        let shift = larger.exponent - smaller.exponent;
+        // The smaller value is below the precision of the larger one
+        if shift > 2 {
+            return larger;
+        }
+        Gold::normalized(
+            larger.mantissa + scale(smaller.mantissa, -shift),
+            larger.exponent,
+        )
+    }
+}
+
+impl Sub for Gold {
+    type Output = Gold;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + (-rhs)
+    }
+}
+
+impl Neg for Gold {
+    type Output = Gold;
+
+    fn neg(self) -> Self::Output {
+        // Normalizing turns -0 into zero, which would be displayed as "-0"
+        Gold::normalized(-self.mantissa, self.exponent)
+    }
+}
+
+impl Mul for Gold {
+    type Output = Gold;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Gold::normalized(self.mantissa * rhs.mantissa, self.exponent + rhs.exponent)
+    }
+}
+
+impl Div for Gold {
+    type Output = Gold;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        Gold::normalized(self.mantissa / rhs.mantissa, self.exponent - rhs.exponent)
+    }
+}
+
+impl Mul<f64> for Gold {
+    type Output = Gold;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        self * Gold::from(rhs)
+    }
+}
+
+impl Div<f64> for Gold {
+    type Output = Gold;
+
+    fn div(self, rhs: f64) -> Self::Output {
+        self / Gold::from(rhs)
+    }
+}
+
+impl AddAssign for Gold {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Gold {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sum for Gold {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Gold::ZERO, |total, gold| total + gold)
+    }
+}
+
+impl PartialOrd for Gold {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if !self.is_finite() || !other.is_finite() {
+            return self.to_f64().partial_cmp(&other.to_f64());
+        }
+
+        let sign = |gold: &Gold| gold.mantissa.partial_cmp(&0.0);
+        let sign_ordering = sign(self)?.cmp(&sign(other)?);
+        if sign_ordering != Ordering::Equal || self.is_zero() {
+            return Some(sign_ordering);
+        }
+
+        let magnitude_ordering = self
+            .exponent
+            .cmp(&other.exponent)
+            .then(self.mantissa.abs().partial_cmp(&other.mantissa.abs())?);
+        if self.is_sign_negative() {
+            Some(magnitude_ordering.reverse())
+        } else {
+            Some(magnitude_ordering)
+        }
+    }
+}
+
+/// Small amounts are displayed like an `f64`, large amounts in scientific
+/// notation, e.g. `1.23e45`. Precision and width are respected.
+impl fmt::Display for Gold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_f64();
+        let text = if !self.is_finite() || value.abs() < SCIENTIFIC_THRESHOLD {
+            match f.precision() {
+                Some(precision) => format!("{:.*}", precision, value),
+                None => format!("{}", value),
+            }
+        } else {
+            let (mantissa, exponent) = self.to_scientific();
+            let sign = if self.is_sign_negative() { "-" } else { "" };
+            match f.precision() {
+                Some(precision) => format!("{}{:.*}e{}", sign, precision, mantissa, exponent),
+                None => format!("{}{}e{}", sign, mantissa, exponent),
+            }
+        };
+
+        match f.width() {
+            Some(width) => write!(f, "{:>width$}", text, width = width),
+            None => f.write_str(&text),
+        }
+    }
+}
+
+/// Amounts that fit into an `f64` are stored as plain JSON numbers, which
+/// keeps saves of earlier versions loadable. Larger amounts are stored as
+/// their mantissa and exponent.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum GoldRepr {
+    Number(f64),
+    Parts { mantissa: f64, exponent: i64 },
+}
+
+impl Serialize for Gold {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = self.to_f64();
+        if value.is_finite() && Gold::from(value) == *self {
+            GoldRepr::Number(value).serialize(serializer)
+        } else {
+            GoldRepr::Parts {
+                mantissa: self.mantissa,
+                exponent: self.exponent,
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Gold {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match GoldRepr::deserialize(deserializer)? {
+            GoldRepr::Number(value) => Ok(Gold::from(value)),
+            GoldRepr::Parts { mantissa, exponent } => Ok(Gold::normalized(mantissa, exponent)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::approx_eq;
+
+    use super::Gold;
+
+    #[test]
+    fn arithmetic_matches_f64_in_f64_range() {
+        let a = 1234.5678;
+        let b = 0.0042;
+
+        assert_eq!(a + b, (Gold::from(a) + Gold::from(b)).to_f64());
+        assert_eq!(a - b, (Gold::from(a) - Gold::from(b)).to_f64());
+        assert_eq!(a * b, (Gold::from(a) * Gold::from(b)).to_f64());
+        assert_eq!(a / b, (Gold::from(a) / Gold::from(b)).to_f64());
+        assert_eq!(f64::powi(1.15, 40), Gold::powi(1.15, 40).to_f64());
+    }
+
+    #[test]
+    fn powers_beyond_f64_stay_finite() {
+        let huge = Gold::powi(4.0, 2_000);
+
+        assert!(huge.is_finite());
+        assert!(huge > Gold::from(f64::MAX));
+        assert!(approx_eq!(
+            f64,
+            2_000.0 * f64::ln(4.0),
+            huge.ln(),
+            epsilon = 1e-9
+        ));
+        assert_eq!(Gold::powi(4.0, 1_999) * 4.0, huge);
+    }
+
+    #[test]
+    fn ordering_across_exponents_and_signs() {
+        let small = Gold::from(0.5);
+        let large = Gold::powi(10.0, 400);
+
+        assert!(small < large);
+        assert!(-large < -small);
+        assert!(-small < Gold::ZERO);
+        assert!(Gold::ZERO < small);
+        assert!(large - large == Gold::ZERO);
+    }
+
+    #[test]
+    fn adding_a_negligible_amount_keeps_the_value() {
+        let large = Gold::powi(10.0, 1_000);
+
+        assert_eq!(large, large + Gold::from(1.0));
+    }
+
+    #[test]
+    fn display_switches_to_scientific_notation() {
+        assert_eq!("1234.50", format!("{:.2}", Gold::from(1234.5)));
+        assert_eq!("    1.50", format!("{:>8.2}", Gold::from(1.5)));
+        assert_eq!("1.00e20", format!("{:.2}", Gold::from(1e20)));
+        assert_eq!("1.00e400", format!("{:.2}", Gold::powi(10.0, 400)));
+    }
+
+    #[test]
+    fn serialization_round_trip() {
+        for gold in [Gold::ZERO, Gold::from(42.25), Gold::powi(3.0, 5_000)] {
+            let json = serde_json::to_string(&gold).unwrap();
+            let deserialized: Gold = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(gold, deserialized);
+        }
+    }
+
+    #[test]
+    fn plain_numbers_deserialize() {
+        let gold: Gold = serde_json::from_str("1500.5").unwrap();
+
+        assert_eq!(1500.5, gold.to_f64());
+        assert_eq!("1500.5", serde_json::to_string(&gold).unwrap());
+    }
+}
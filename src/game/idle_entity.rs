@@ -1,16 +1,31 @@
-use crate::game::Gold;
+use crate::game::{
+    catalog::{CatalogEntry, Milestone, ResourceAmount},
+    production::total_rate,
+    Gold,
+};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct IdleEntity {
     name: String,
     level: u32,
     base_gold_per_second: Gold,
     base_upgrade_cost: Gold,
-    upgrade_cost_factor: Gold,
+    upgrade_cost_factor: f64,
+    milestones: Vec<Milestone>,
+    produces: Option<ResourceAmount>,
+    consumes: Vec<ResourceAmount>,
+    /// Applied to all upgrade costs, set by the game from its edicts
+    #[serde(skip, default = "no_cost_multiplier")]
+    cost_multiplier: f64,
+}
+
+fn no_cost_multiplier() -> f64 {
+    1.0
 }
 
 impl IdleEntity {
+    #[allow(clippy::redundant_field_names)]
     pub fn build(
         name: String,
         base_gold_per_second: Gold,
@@ -23,49 +38,212 @@ impl IdleEntity {
             base_gold_per_second: base_gold_per_second,
             base_upgrade_cost: base_upgrade_cost,
             upgrade_cost_factor: upgrade_cost_factor,
+            milestones: Vec::new(),
+            produces: None,
+            consumes: Vec::new(),
+            cost_multiplier: 1.0,
         }
     }
 
-    fn add_level(&mut self, amount: u32) {
-        self.level += amount;
+    pub fn from_catalog_entry(entry: &CatalogEntry, level: u32) -> Self {
+        let mut entity = IdleEntity::build(
+            entry.name.clone(),
+            entry.base_gold_per_second,
+            entry.base_upgrade_cost,
+            entry.upgrade_cost_factor,
+        );
+        entity.milestones = entry.milestones.clone();
+        entity.produces = entry.produces.clone();
+        entity.consumes = entry.consumes.clone();
+        entity.add_level(level);
+        entity
+    }
+
+    pub fn add_level(&mut self, amount: u32) {
+        self.level = self.level.saturating_add(amount);
+    }
+
+    pub fn remove_levels(&mut self, amount: u32) {
+        self.level = self.level.saturating_sub(amount);
+    }
+
+    pub fn reset_level(&mut self) {
+        self.level = 0;
+    }
+
+    /// Shrinks the share by which the upgrade cost grows per level
+    pub fn reduce_upgrade_cost_factor(&mut self, reduction: f64) {
+        self.upgrade_cost_factor = 1.0 + (self.upgrade_cost_factor - 1.0) * (1.0 - reduction);
+    }
+
+    pub fn set_cost_multiplier(&mut self, cost_multiplier: f64) {
+        self.cost_multiplier = cost_multiplier;
+    }
+
+    fn base_cost(&self) -> Gold {
+        self.base_upgrade_cost * self.cost_multiplier
     }
 
     pub fn cost_for_next_upgrade(&self) -> Gold {
-        self.base_upgrade_cost * f64::powi(self.upgrade_cost_factor, self.level as i32)
+        self.base_cost() * Gold::powi(self.upgrade_cost_factor, self.level)
     }
 
     /// Calculates the cummulative cost of upgrades
     pub fn cost_for_next_upgrades(&self, levels_to_upgrade: u32) -> Gold {
-        // offset levels by -1 to consider that upgrade
-        // calculations starts with 0 and level with 1
-        let from_level = self.level as i32;
-        let to_level = (self.level + levels_to_upgrade) as i32;
+        self.cost_between_levels(self.level, self.level.saturating_add(levels_to_upgrade))
+    }
 
-        let b = self.base_upgrade_cost;
-        let u = self.upgrade_cost_factor;
+    /// What the last `levels` levels cost at the current prices
+    pub fn cost_of_last_levels(&self, levels: u32) -> Gold {
+        self.cost_between_levels(self.level.saturating_sub(levels), self.level)
+    }
 
-        // geometric series runs to n-1
-        let from_value = b * (1.0 - f64::powi(u, from_level)) / (1.0 - u);
-        let to_value = b * (1.0 - f64::powi(u, to_level)) / (1.0 - u);
+    fn cost_between_levels(&self, from_level: u32, to_level: u32) -> Gold {
+        let levels = to_level.saturating_sub(from_level);
+        let u = self.upgrade_cost_factor;
 
-        to_value - from_value
+        // geometric series over the levels, (u^levels - 1) / (u - 1), kept
+        // exact for a single level so it matches `cost_for_next_upgrade`
+        let growth = if u == 1.0 || levels <= 1 {
+            Gold::from(levels as f64)
+        } else {
+            // a difference of two sums would cancel out for factors close to 1
+            let exponent = levels as f64 * (u - 1.0).ln_1p();
+            if exponent.abs() < 1.0 {
+                Gold::from(exponent.exp_m1() / (u - 1.0))
+            } else {
+                (Gold::powi(u, levels) - Gold::from(1.0)) / (u - 1.0)
+            }
+        };
+
+        self.base_cost() * Gold::powi(u, from_level) * growth
     }
 
+    /// How many upgrades in a row `gold` pays for. The closed form only
+    /// gives an estimate, which is checked against `cost_for_next_upgrades`
+    /// so the result is exact at the boundaries.
     pub fn quanity_of_possible_upgrades(&self, gold: &Gold) -> u32 {
-        let b = self.base_upgrade_cost;
+        let next_cost = self.cost_for_next_upgrade();
+        if !gold.is_finite()
+            || !next_cost.is_finite()
+            || next_cost <= Gold::ZERO
+            || *gold < next_cost
+        {
+            return 0;
+        }
+
+        let max = u32::MAX - self.level;
+        let affordable = |levels: u32| self.cost_for_next_upgrades(levels) <= *gold;
+        let estimate = self.estimate_possible_upgrades(*gold / next_cost).min(max);
+
+        // widen around the estimate until `low` is affordable and `high` is not
+        let mut step = 1;
+        let (mut low, mut high) = if affordable(estimate) {
+            let mut low = estimate;
+            loop {
+                let high = low.saturating_add(step).min(max);
+                if high == low {
+                    return max;
+                }
+                if !affordable(high) {
+                    break (low, high);
+                }
+                low = high;
+                step = step.saturating_mul(2);
+            }
+        } else {
+            let mut high = estimate;
+            loop {
+                let low = high.saturating_sub(step);
+                if low == 0 || affordable(low) {
+                    break (low, high);
+                }
+                high = low;
+                step = step.saturating_mul(2);
+            }
+        };
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if affordable(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    /// Solves the geometric series for the number of levels, `ratio` is the
+    /// gold divided by the cost of the next level
+    fn estimate_possible_upgrades(&self, ratio: Gold) -> u32 {
         let u = self.upgrade_cost_factor;
-        let l = self.level as i32;
-        let n = f64::log((gold / (b * f64::powi(u, l))) * (u - 1.0) + 1.0, u);
-        n as u32
+        let levels = if u == 1.0 {
+            ratio.to_f64()
+        } else {
+            let base = ratio * (u - 1.0) + Gold::from(1.0);
+            if base > Gold::ZERO {
+                base.ln() / u.ln()
+            } else {
+                // shrinking costs whose sum stays below the gold
+                f64::INFINITY
+            }
+        };
+        // saturates, NaN becomes 0
+        levels as u32
     }
 
-    /// Calculates the gold gain in deltaTime milliseconds
-    pub fn get_gold(&self, delta_time: u128) -> Gold {
-        self.gold_per_second() * (delta_time as f64) / 1000.0
+    /// Calculates the gold gain of running at full capacity for `seconds`
+    pub fn get_gold(&self, seconds: f64) -> Gold {
+        self.gold_per_second() * seconds
     }
 
     pub fn gold_per_second(&self) -> Gold {
-        ((self.level + 1) as f64) * self.base_gold_per_second
+        self.gold_per_second_at(self.level)
+    }
+
+    fn gold_per_second_at(&self, level: u32) -> Gold {
+        self.base_gold_per_second * self.capacity_at(level)
+    }
+
+    /// Factor by which the entity outgrew level 0, scales gold and resources
+    fn capacity_at(&self, level: u32) -> f64 {
+        ((level as f64) + 1.0) * self.milestone_multiplier(level)
+    }
+
+    /// Units of the produced resource per second
+    pub fn production(&self) -> Option<(&str, f64)> {
+        self.produces.as_ref().map(|produced| {
+            (
+                produced.resource.as_str(),
+                produced.amount * self.capacity_at(self.level),
+            )
+        })
+    }
+
+    /// Units of the given resource needed per second at full capacity
+    pub fn consumption(&self, resource: &str) -> f64 {
+        let amounts = self
+            .consumes
+            .iter()
+            .filter(|consumed| consumed.resource == resource)
+            .map(|consumed| consumed.amount * self.capacity_at(self.level));
+        total_rate(amounts)
+    }
+
+    /// Product of the multipliers of all milestones reached at `level`
+    pub fn milestone_multiplier(&self, level: u32) -> f64 {
+        self.milestones
+            .iter()
+            .take_while(|milestone| milestone.level <= level)
+            .map(|milestone| milestone.multiplier)
+            .product()
+    }
+
+    /// The first milestone above the current level
+    pub fn next_milestone(&self) -> Option<&Milestone> {
+        self.milestones
+            .iter()
+            .find(|milestone| milestone.level > self.level)
     }
 
     pub fn get_name(&self) -> &str {
@@ -77,6 +255,7 @@ impl IdleEntity {
     }
 
     /// Upgrades the entity by 1 and returns the remaining gold
+    #[allow(clippy::needless_return)]
     pub fn upgrade(&mut self, gold: &mut Gold) -> Result<(), ()> {
         if *gold >= self.cost_for_next_upgrade() {
             *gold -= self.cost_for_next_upgrade();
@@ -92,29 +271,31 @@ mod tests {
     use float_cmp::approx_eq;
 
     use super::IdleEntity;
-    use crate::game::Gold;
+    use crate::game::{catalog::Milestone, random::Random, Gold};
 
     fn create_entity() -> IdleEntity {
         IdleEntity {
             name: "Entity".to_owned(),
             level: 0,
-            base_gold_per_second: 0.1,
-            base_upgrade_cost: 1.0,
+            base_gold_per_second: Gold::from(0.1),
+            base_upgrade_cost: Gold::from(1.0),
             upgrade_cost_factor: 2.0,
+            milestones: Vec::new(),
+            produces: None,
+            consumes: Vec::new(),
+            cost_multiplier: 1.0,
         }
     }
 
     #[test]
     fn gold_gain_after_1_seconds() {
         let entity = create_entity();
-        let delta_time = 1000 * 1;
-
-        let gold_gain = entity.get_gold(delta_time);
+        let gold_gain = entity.get_gold(1.0);
 
         assert!(approx_eq!(
-            Gold,
-            entity.gold_per_second() * 1.0,
-            gold_gain,
+            f64,
+            (entity.gold_per_second() * 1.0).to_f64(),
+            gold_gain.to_f64(),
             ulps = 2
         ));
     }
@@ -122,14 +303,12 @@ mod tests {
     #[test]
     fn gold_gain_after_3_seconds() {
         let entity = create_entity();
-        let delta_time = 1000 * 3;
-
-        let gold_gain = entity.get_gold(delta_time);
+        let gold_gain = entity.get_gold(3.0);
 
         assert!(approx_eq!(
-            Gold,
-            entity.gold_per_second() * 3.0,
-            gold_gain,
+            f64,
+            (entity.gold_per_second() * 3.0).to_f64(),
+            gold_gain.to_f64(),
             ulps = 2
         ));
     }
@@ -141,9 +320,9 @@ mod tests {
 
         let gold_per_second = entity.base_gold_per_second * (entity.level + 1) as f64;
         assert!(approx_eq!(
-            Gold,
-            gold_per_second,
-            entity.gold_per_second(),
+            f64,
+            (gold_per_second).to_f64(),
+            entity.gold_per_second().to_f64(),
             ulps = 2
         ))
     }
@@ -154,9 +333,10 @@ mod tests {
         entity.add_level(2);
 
         assert!(approx_eq!(
-            Gold,
-            entity.base_upgrade_cost * entity.upgrade_cost_factor * entity.upgrade_cost_factor,
-            entity.cost_for_next_upgrade(),
+            f64,
+            (entity.base_upgrade_cost * entity.upgrade_cost_factor * entity.upgrade_cost_factor)
+                .to_f64(),
+            entity.cost_for_next_upgrade().to_f64(),
             ulps = 2
         ))
     }
@@ -177,26 +357,49 @@ mod tests {
             + entity.base_upgrade_cost * f64::powi(entity.upgrade_cost_factor, 9);
 
         assert_eq!(total_cost, total_upgrade_cost);
-        assert!(approx_eq!(Gold, total_cost, total_upgrade_cost, ulps = 2))
+        assert!(approx_eq!(
+            f64,
+            (total_cost).to_f64(),
+            total_upgrade_cost.to_f64(),
+            ulps = 2
+        ))
+    }
+
+    #[test]
+    fn cost_of_last_levels_matches_their_upgrade_cost() {
+        let mut entity = create_entity();
+        let upgrade_cost = entity.cost_for_next_upgrades(5);
+
+        entity.add_level(5);
+
+        assert_eq!(upgrade_cost, entity.cost_of_last_levels(5));
+        assert_eq!(upgrade_cost, entity.cost_of_last_levels(10));
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn upgrade_entity_once() {
         let mut entity = create_entity();
-        let start_gold: Gold = 2.0;
+        let start_gold: Gold = Gold::from(2.0);
         let mut gold: Gold = start_gold.clone();
 
         let _ = entity.upgrade(&mut gold);
         let total_cost: Gold = entity.base_upgrade_cost;
 
         assert_eq!(1, entity.level);
-        assert!(approx_eq!(Gold, start_gold - total_cost, gold, ulps = 2));
+        assert!(approx_eq!(
+            f64,
+            (start_gold - total_cost).to_f64(),
+            gold.to_f64(),
+            ulps = 2
+        ));
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn upgrade_entity_three_times() {
         let mut entity = create_entity();
-        let start_gold: Gold = 100.0;
+        let start_gold: Gold = Gold::from(100.0);
         let mut gold: Gold = start_gold.clone();
 
         let _ = entity.upgrade(&mut gold);
@@ -207,14 +410,19 @@ mod tests {
         let total_cost: Gold = entity.base_upgrade_cost
             + entity.base_upgrade_cost * entity.upgrade_cost_factor
             + entity.base_upgrade_cost * entity.upgrade_cost_factor * entity.upgrade_cost_factor;
-        assert!(approx_eq!(Gold, start_gold - total_cost, gold, ulps = 2));
+        assert!(approx_eq!(
+            f64,
+            (start_gold - total_cost).to_f64(),
+            gold.to_f64(),
+            ulps = 2
+        ));
     }
 
     #[test]
     fn quanity_of_possible_upgrades_from_level_0() {
         let mut entity = create_entity();
         entity.upgrade_cost_factor = 2.0;
-        let gold: Gold = 15.0;
+        let gold: Gold = Gold::from(15.0);
 
         let quantity = entity.quanity_of_possible_upgrades(&gold);
 
@@ -233,7 +441,7 @@ mod tests {
         let mut entity = create_entity();
         entity.upgrade_cost_factor = 2.0;
         entity.add_level(3);
-        let gold: Gold = 56.0;
+        let gold: Gold = Gold::from(56.0);
 
         let quantity = entity.quanity_of_possible_upgrades(&gold);
         let total_cost_of_possible_upgrades: Gold = entity.base_upgrade_cost
@@ -244,4 +452,143 @@ mod tests {
         assert_eq!(3, quantity);
         assert_eq!(total_cost_of_possible_upgrades, gold);
     }
+
+    #[test]
+    fn upgrade_costs_of_high_levels_do_not_overflow() {
+        let mut entity = create_entity();
+        entity.base_upgrade_cost = Gold::from(1e12);
+        entity.upgrade_cost_factor = 4.0;
+        entity.add_level(1_000);
+
+        let next_cost = entity.cost_for_next_upgrade();
+        let total_cost = entity.cost_for_next_upgrades(10);
+
+        assert!(next_cost.is_finite());
+        assert!(total_cost > next_cost);
+        assert_eq!(
+            10,
+            entity.quanity_of_possible_upgrades(&(total_cost * 1.01))
+        );
+    }
+
+    #[test]
+    fn quanity_of_possible_upgrades_matches_brute_force() {
+        let mut random = Random::new(7);
+        for _ in 0..2_000 {
+            let mut entity = create_entity();
+            entity.base_upgrade_cost = Gold::from(0.1 + random.next_f64() * 100.0);
+            entity.upgrade_cost_factor = match random.next_u64() % 4 {
+                0 => 1.0,
+                1 => 1.0 + random.next_f64() * 1e-3,
+                _ => 1.0 + random.next_f64(),
+            };
+            entity.add_level((random.next_u64() % 200) as u32);
+            // every other run hits a boundary exactly
+            let gold = if random.next_u64().is_multiple_of(2) {
+                entity.cost_for_next_upgrades((random.next_u64() % 100) as u32)
+            } else {
+                entity.cost_for_next_upgrade() * (random.next_f64() * 100.0)
+            };
+
+            let brute_force = (0..)
+                .find(|levels| entity.cost_for_next_upgrades(levels + 1) > gold)
+                .unwrap();
+
+            assert_eq!(
+                brute_force,
+                entity.quanity_of_possible_upgrades(&gold),
+                "{:?} with {} gold",
+                entity,
+                gold
+            );
+        }
+    }
+
+    #[test]
+    fn cumulative_costs_grow_where_the_series_changes_formula() {
+        let mut random = Random::new(11);
+        for _ in 0..2_000 {
+            let mut entity = create_entity();
+            entity.upgrade_cost_factor = match random.next_u64() % 3 {
+                0 => 1.0 - random.next_f64() * 0.5,
+                1 => 1.0 + 1e-6 + random.next_f64() * 1e-3,
+                _ => 1.0 + random.next_f64(),
+            };
+            entity.add_level((random.next_u64() % 200) as u32);
+            // the sum switches from `exp_m1` to `powi` once the exponent
+            // reaches 1
+            let switch = (1.0 / (entity.upgrade_cost_factor - 1.0).ln_1p()).abs() as u32;
+
+            for levels in switch.saturating_sub(3)..switch + 3 {
+                assert!(
+                    entity.cost_for_next_upgrades(levels)
+                        < entity.cost_for_next_upgrades(levels + 1),
+                    "{:?} at {} levels",
+                    entity,
+                    levels
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn constant_upgrade_costs() {
+        let mut entity = create_entity();
+        entity.upgrade_cost_factor = 1.0;
+        entity.add_level(5);
+
+        assert_eq!(Gold::from(3.0), entity.cost_for_next_upgrades(3));
+        assert_eq!(Gold::from(2.0), entity.cost_of_last_levels(2));
+        assert_eq!(3, entity.quanity_of_possible_upgrades(&Gold::from(3.5)));
+    }
+
+    #[test]
+    fn no_upgrades_for_non_finite_gold() {
+        let entity = create_entity();
+
+        assert_eq!(
+            0,
+            entity.quanity_of_possible_upgrades(&Gold::from(f64::NAN))
+        );
+        assert_eq!(
+            0,
+            entity.quanity_of_possible_upgrades(&Gold::from(f64::INFINITY))
+        );
+        assert_eq!(0, entity.quanity_of_possible_upgrades(&Gold::from(-5.0)));
+    }
+
+    #[test]
+    fn milestones_multiply_gold_per_second() {
+        let mut entity = create_entity();
+        entity.milestones = vec![
+            Milestone {
+                level: 2,
+                multiplier: 2.0,
+            },
+            Milestone {
+                level: 4,
+                multiplier: 3.0,
+            },
+        ];
+
+        entity.add_level(1);
+        assert_eq!(Gold::from(0.2), entity.gold_per_second());
+        assert_eq!(2, entity.next_milestone().unwrap().level);
+        // Reaching the milestone doubles the output of all levels
+        assert!(approx_eq!(
+            f64,
+            0.4,
+            (entity.gold_per_second_at(2) - entity.gold_per_second()).to_f64(),
+            ulps = 2
+        ));
+
+        entity.add_level(3);
+        assert!(approx_eq!(
+            f64,
+            0.1 * 5.0 * 6.0,
+            entity.gold_per_second().to_f64(),
+            ulps = 2
+        ));
+        assert!(entity.next_milestone().is_none());
+    }
 }
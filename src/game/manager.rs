@@ -0,0 +1,406 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{
+    game_state::{ManagerInformation, ManagerOverview, ManagerPurchaseInformation, UpgradeSummary},
+    research::{ResearchEffect, TECH_TREE},
+    Game, Gold,
+};
+
+/// Time between two rounds of the managers. Rounds happen at multiples of it,
+/// so they do not depend on how often the game is updated.
+const MANAGER_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of purchases kept in the log of the save
+const MANAGER_LOG_LENGTH: usize = 50;
+
+/// A rule the manager of an entity follows, as stored in the save
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ManagerRule {
+    /// Buys levels while the entity is more than `levels` behind `other`
+    KeepWithin {
+        entity: String,
+        other: String,
+        levels: u32,
+    },
+    /// Spends up to a share of the gold while the upgrades pay for themselves
+    /// within `max_payback_seconds`
+    SpendShare {
+        entity: String,
+        share: f64,
+        max_payback_seconds: u64,
+    },
+}
+
+impl ManagerRule {
+    fn entity(&self) -> &str {
+        match self {
+            ManagerRule::KeepWithin { entity, .. } | ManagerRule::SpendShare { entity, .. } => {
+                entity
+            }
+        }
+    }
+
+    fn is_same_kind(&self, other: &ManagerRule) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            ManagerRule::KeepWithin { other, levels, .. } => {
+                format!("keep within {} levels of {}", levels, other)
+            }
+            ManagerRule::SpendShare {
+                share,
+                max_payback_seconds,
+                ..
+            } => format!(
+                "spend up to {:.0}% of the gold while the payback is under {}",
+                share * 100.0,
+                format_duration(*max_payback_seconds)
+            ),
+        }
+    }
+}
+
+/// Formats seconds the way durations are given on the command line, e.g. 1h30m
+fn format_duration(seconds: u64) -> String {
+    let mut remaining = seconds;
+    let mut formatted = String::new();
+    for (unit, unit_seconds) in [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)] {
+        if remaining >= unit_seconds {
+            formatted.push_str(&format!("{}{}", remaining / unit_seconds, unit));
+            remaining %= unit_seconds;
+        }
+    }
+    if formatted.is_empty() {
+        formatted.push_str("0s");
+    }
+    formatted
+}
+
+/// Levels a manager bought in one round
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManagerPurchase {
+    pub at: SystemTime,
+    pub entity: String,
+    pub levels: u32,
+    pub gold_spent: Gold,
+}
+
+/// Entities with a manager and the research node which unlocks it
+fn automatable_entities() -> impl Iterator<Item = (&'static str, &'static str)> {
+    TECH_TREE.iter().filter_map(|node| match node.effect {
+        ResearchEffect::Automation { entity } => Some((entity, node.name)),
+        _ => None,
+    })
+}
+
+impl Game {
+    /// Whether the manager of the entity was unlocked by research
+    fn is_manager_unlocked(&self, entity_name: &str) -> bool {
+        self.automated_entities()
+            .any(|entity| entity.eq_ignore_ascii_case(entity_name))
+    }
+
+    /// Adds a rule to the manager of an entity, replacing its rule of the
+    /// same kind
+    pub fn add_manager_rule(&mut self, mut rule: ManagerRule) -> Result<(), String> {
+        let entity_name = |game: &Game, name: &str| {
+            game.entity_index(name)
+                .map(|idx| game.idle_entities[idx].get_name().to_string())
+                .ok_or_else(|| format!("Unknown entity {}", name))
+        };
+        let entity = entity_name(self, rule.entity())?;
+        if !self.is_manager_unlocked(&entity) {
+            return Err(
+                match automatable_entities().find(|(other, _)| *other == entity) {
+                    Some((_, node)) => {
+                        format!("Research {} to unlock the {} manager", node, entity)
+                    }
+                    None => format!("There is no manager for {}", entity),
+                },
+            );
+        }
+
+        match &mut rule {
+            ManagerRule::KeepWithin {
+                entity: rule_entity,
+                other,
+                ..
+            } => {
+                *other = entity_name(self, other)?;
+                if *other == entity {
+                    return Err(format!("{} cannot follow itself", entity));
+                }
+                *rule_entity = entity;
+            }
+            ManagerRule::SpendShare {
+                entity: rule_entity,
+                share,
+                max_payback_seconds,
+            } => {
+                if !(*share > 0.0 && *share <= 1.0) {
+                    return Err("The share of the gold has to be between 0% and 100%".into());
+                }
+                if *max_payback_seconds == 0 {
+                    return Err("The payback time has to be longer than 0s".into());
+                }
+                *rule_entity = entity;
+            }
+        }
+
+        match self
+            .managers
+            .iter_mut()
+            .find(|existing| existing.entity() == rule.entity() && existing.is_same_kind(&rule))
+        {
+            Some(existing) => *existing = rule,
+            None => self.managers.push(rule),
+        }
+        Ok(())
+    }
+
+    /// Removes all rules of the manager of an entity
+    pub fn dismiss_manager(&mut self, entity_name: &str) -> Result<(), String> {
+        let rule_count = self.managers.len();
+        self.managers
+            .retain(|rule| !rule.entity().eq_ignore_ascii_case(entity_name));
+        if self.managers.len() == rule_count {
+            return Err(format!("The {} manager has no rules", entity_name));
+        }
+        Ok(())
+    }
+
+    /// The next round of the managers after `time`, if any manager has rules
+    pub(super) fn next_manager_round(&self, time: SystemTime) -> Option<SystemTime> {
+        if self.managers.is_empty() {
+            return None;
+        }
+        let interval = MANAGER_INTERVAL.as_secs();
+        let elapsed = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs((elapsed / interval + 1) * interval))
+    }
+
+    /// Lets the managers buy upgrades if a round is due at `time`
+    pub(super) fn run_managers(&mut self, time: SystemTime) {
+        let since_epoch = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        if !since_epoch
+            .as_nanos()
+            .is_multiple_of(MANAGER_INTERVAL.as_nanos())
+        {
+            return;
+        }
+
+        for rule in self.managers.clone() {
+            if !self.is_manager_unlocked(rule.entity()) {
+                continue;
+            }
+            let Some(idx) = self.entity_index(rule.entity()) else {
+                continue;
+            };
+            let gold_before = self.current_gold;
+            let levels = match &rule {
+                ManagerRule::KeepWithin { other, levels, .. } => {
+                    let target = self.entity_level(other).saturating_sub(*levels);
+                    let missing = target.saturating_sub(self.idle_entities[idx].get_level());
+                    self.upgrade_entity(idx, missing)
+                }
+                ManagerRule::SpendShare {
+                    share,
+                    max_payback_seconds,
+                    ..
+                } => self.spend_share(idx, *share, *max_payback_seconds as f64),
+            };
+            if levels > 0 {
+                self.log_manager_purchase(ManagerPurchase {
+                    at: time,
+                    entity: rule.entity().to_string(),
+                    levels,
+                    gold_spent: gold_before - self.current_gold,
+                });
+            }
+        }
+    }
+
+    /// Buys levels of an entity while they fit into the share of the gold
+    /// and pay for themselves fast enough
+    fn spend_share(&mut self, idx: usize, share: f64, max_payback_seconds: f64) -> u32 {
+        let budget = self.current_gold * share;
+        let mut gold_spent = Gold::ZERO;
+        let mut levels = 0;
+        loop {
+            let cost = self.idle_entities[idx].cost_for_next_upgrade();
+            let gain = self.gold_per_second_gain(idx, 1);
+            if gold_spent + cost > budget
+                || gain <= Gold::ZERO
+                || cost.ratio(gain) > max_payback_seconds
+                || self.upgrade_entity(idx, 1) == 0
+            {
+                break;
+            }
+            gold_spent += cost;
+            levels += 1;
+        }
+        levels
+    }
+
+    fn log_manager_purchase(&mut self, purchase: ManagerPurchase) {
+        match self
+            .recent_manager_purchases
+            .iter_mut()
+            .find(|summary| summary.name == purchase.entity)
+        {
+            Some(summary) => {
+                summary.levels += purchase.levels;
+                summary.gold_spent += purchase.gold_spent;
+            }
+            None => self.recent_manager_purchases.push(UpgradeSummary {
+                name: purchase.entity.clone(),
+                levels: purchase.levels,
+                gold_spent: purchase.gold_spent,
+            }),
+        }
+
+        self.manager_log.push(purchase);
+        let overflow = self.manager_log.len().saturating_sub(MANAGER_LOG_LENGTH);
+        self.manager_log.drain(..overflow);
+    }
+
+    pub fn manager_overview(&self) -> ManagerOverview {
+        ManagerOverview {
+            managers: automatable_entities()
+                .map(|(entity, node)| ManagerInformation {
+                    entity: entity.to_string(),
+                    unlocked: self.is_manager_unlocked(entity),
+                    unlocked_by: node.to_string(),
+                    rules: self
+                        .managers
+                        .iter()
+                        .filter(|rule| rule.entity() == entity)
+                        .map(ManagerRule::describe)
+                        .collect(),
+                })
+                .collect(),
+            purchases: self
+                .manager_log
+                .iter()
+                .map(|purchase| ManagerPurchaseInformation {
+                    at_unix_seconds: purchase
+                        .at
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                    entity: purchase.entity.clone(),
+                    levels: purchase.levels,
+                    gold_spent: purchase.gold_spent,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{format_duration, ManagerRule};
+    use crate::game::{test_support::create_game_with_clock, Gold};
+
+    fn keep_within(levels: u32) -> ManagerRule {
+        ManagerRule::KeepWithin {
+            entity: "lumberjack".to_string(),
+            other: "stonemason".to_string(),
+            levels,
+        }
+    }
+
+    #[test]
+    fn rules_need_an_unlocked_manager() {
+        let (mut game, _) = create_game_with_clock();
+        game.research.push("foremen".to_string());
+        game.pause_events();
+        game.research.clear();
+        assert!(game.add_manager_rule(keep_within(10)).is_err());
+
+        game.research.push("foremen".to_string());
+        assert!(game.add_manager_rule(keep_within(10)).is_ok());
+        // A rule of the same kind replaces the previous one
+        assert!(game.add_manager_rule(keep_within(5)).is_ok());
+        assert_eq!(
+            vec!["keep within 5 levels of Stonemason".to_string()],
+            game.manager_overview().managers[0].rules
+        );
+        assert!(game
+            .add_manager_rule(ManagerRule::KeepWithin {
+                entity: "lumberjack".to_string(),
+                other: "lumberjack".to_string(),
+                levels: 1,
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn managers_buy_at_their_rounds_while_away() {
+        let (mut game, clock) = create_game_with_clock();
+        game.research.push("foremen".to_string());
+        game.pause_events();
+        game.current_gold = Gold::from(1e6);
+        game.upgrade("stonemason", 20);
+        game.add_manager_rule(keep_within(10)).unwrap();
+
+        clock.advance(Duration::from_secs(59));
+        game.update();
+        assert_eq!(0, game.entity_level("Lumberjack"));
+
+        clock.advance(Duration::from_secs(1));
+        let game_state = game.update();
+        assert_eq!(10, game.entity_level("Lumberjack"));
+        assert_eq!(10, game_state.manager_purchases[0].levels);
+        assert_eq!(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(60),
+            game.manager_log[0].at
+        );
+    }
+
+    #[test]
+    fn offline_time_compounds_like_continuous_play() {
+        let (mut game, clock) = create_game_with_clock();
+        game.research.push("foremen".to_string());
+        game.pause_events();
+        game.add_manager_rule(ManagerRule::SpendShare {
+            entity: "lumberjack".to_string(),
+            share: 0.5,
+            max_payback_seconds: 3_600,
+        })
+        .unwrap();
+        let mut other_game = game.clone();
+
+        for _ in 0..60 {
+            clock.advance(Duration::from_secs(60));
+            game.update();
+        }
+        other_game.update();
+
+        assert!(game.entity_level("Lumberjack") > 0);
+        assert_eq!(
+            game.entity_level("Lumberjack"),
+            other_game.entity_level("Lumberjack")
+        );
+        assert!((game.current_gold - other_game.current_gold).to_f64().abs() < 1e-6);
+    }
+
+    #[test]
+    fn durations_are_formatted_like_arguments() {
+        assert_eq!("1h30m", format_duration(5_400));
+        assert_eq!("1d12h", format_duration(129_600));
+        assert_eq!("0s", format_duration(0));
+    }
+}
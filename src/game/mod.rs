@@ -1,183 +1,761 @@
+pub mod achievement;
+pub mod advisor;
+pub mod campaign;
+pub mod catalog;
+pub mod clock;
+pub mod edict;
+pub mod event;
 pub mod game_state;
+pub mod gold;
 pub mod idle_entity;
+pub mod manager;
+pub mod offline;
+pub mod prestige;
+pub mod production;
+pub mod random;
+pub mod research;
+pub mod simulation;
+pub mod work;
 
 use serde::{Deserialize, Serialize};
 use std::{
-    fmt,
+    cmp::Ordering,
+    collections::BTreeMap,
+    rc::Rc,
     time::{Duration, SystemTime},
-    vec,
 };
 
 use self::{
-    game_state::{GameInformation, GameState, IdleEntityInformation},
+    achievement::UnlockedAchievement,
+    campaign::ActiveCampaign,
+    catalog::{Catalog, OfflineSettings},
+    clock::Clock,
+    event::{EventLogEntry, EventState},
+    game_state::{
+        CampaignOutcome, GameInformation, GameState, IdleEntityInformation, MilestoneInformation,
+        OfflineProgress, PrestigeSummary, SellSummary, UpgradeSummary,
+    },
     idle_entity::IdleEntity,
+    manager::{ManagerPurchase, ManagerRule},
+    work::WorkState,
 };
 
-pub type Gold = f64;
-pub enum IdleEntityType {
-    Lumberjack = 0,
-    Stonemason = 1,
-    Bowmaker = 2,
-    Weaponsmith = 3,
-    Academic = 4,
-    Catapult = 5,
-    King = 6,
-}
+pub use self::gold::Gold;
 
-impl fmt::Display for IdleEntityType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            IdleEntityType::Lumberjack => write!(f, "Lumberjack"),
-            IdleEntityType::Stonemason => write!(f, "Stonemason"),
-            IdleEntityType::Bowmaker => write!(f, "Bowmaker"),
-            IdleEntityType::Weaponsmith => write!(f, "Weaponsmith"),
-            IdleEntityType::Academic => write!(f, "Academic"),
-            IdleEntityType::Catapult => write!(f, "Catapult"),
-            IdleEntityType::King => write!(f, "King"),
-        }
-    }
+/// Decides which entity `Game::upgrade_all` buys next
+#[derive(Clone, Copy, Debug)]
+pub enum UpgradeStrategy {
+    /// Always buy the cheapest affordable upgrade
+    Cheapest,
+    /// Buy the upgrade with the most gold per second gained per gold spent
+    Efficiency,
+    /// Upgrade every affordable entity in turn
+    RoundRobin,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Game {
     init_time_stamp: SystemTime,
     last_time_stamp: SystemTime,
     current_delta_time: Duration,
     current_gold: Gold,
     idle_entities: Vec<IdleEntity>,
+    /// Stockpile of every resource made by the entities
+    resources: BTreeMap<String, f64>,
+    /// All gold ever earned, survives prestige resets
+    lifetime_gold: Gold,
+    /// Prestige points claimed by previous resets
+    prestige_points: u64,
+    achievements: Vec<UnlockedAchievement>,
+    /// Ids of the researched nodes of the tech tree
+    research: Vec<String>,
+    campaign: Option<ActiveCampaign>,
+    /// Ids of the regions won by campaigns
+    conquered_regions: Vec<String>,
+    /// Ids of the edicts of the King, in the order of their slots
+    edicts: Vec<String>,
+    edicts_changed_at: Option<SystemTime>,
+    events: EventState,
+    /// Rules of the managers, applied in order
+    managers: Vec<ManagerRule>,
+    /// The latest purchases of the managers, oldest first
+    manager_log: Vec<ManagerPurchase>,
+    work: WorkState,
+    /// Names of the achievements unlocked since the game was loaded
+    #[serde(skip)]
+    recent_achievements: Vec<String>,
+    /// Outcome of the campaign decided since the game was loaded
+    #[serde(skip)]
+    campaign_outcome: Option<CampaignOutcome>,
+    /// Events rolled since the game was loaded
+    #[serde(skip)]
+    recent_events: Vec<EventLogEntry>,
+    /// Set on copies of the game that forecast without events
+    #[serde(skip)]
+    events_paused: bool,
+    /// Levels the managers bought since the game was loaded, per entity
+    #[serde(skip)]
+    recent_manager_purchases: Vec<UpgradeSummary>,
+    /// Offline settings of the catalog, set whenever the catalog is applied
+    #[serde(skip)]
+    offline_settings: OfflineSettings,
+    /// Share of the upgrade cost refunded for sold levels, from the catalog
+    #[serde(skip)]
+    sell_refund_share: f64,
+    /// How much of the time away the last update credited
+    #[serde(skip)]
+    offline_progress: Option<OfflineProgress>,
+    /// Set on copies of the game that forecast continuous play
+    #[serde(skip)]
+    offline_cap_lifted: bool,
+    #[serde(skip, default = "clock::system_clock")]
+    clock: Rc<dyn Clock>,
+    /// How far the clock went backwards at the last update
+    #[serde(skip)]
+    clock_skew: Option<Duration>,
 }
 
 // Game associated functions
 impl Game {
-    pub fn new() -> Self {
+    pub fn new(catalog: &Catalog) -> Self {
+        Game::with_clock(catalog, clock::system_clock())
+    }
+
+    pub fn with_clock(catalog: &Catalog, clock: Rc<dyn Clock>) -> Self {
         Game {
-            init_time_stamp: SystemTime::now(),
-            last_time_stamp: SystemTime::now(),
-            current_gold: 0.0,
-            idle_entities: vec![
-                IdleEntity::build(IdleEntityType::Lumberjack.to_string(), 0.1, 1.00, 1.15),
-                IdleEntity::build(IdleEntityType::Stonemason.to_string(), 1.0, 10.00, 1.35),
-                IdleEntity::build(IdleEntityType::Bowmaker.to_string(), 20.0, 1_000.00, 1.55),
-                IdleEntity::build(
-                    IdleEntityType::Weaponsmith.to_string(),
-                    350.0,
-                    100_000.00,
-                    1.8,
-                ),
-                IdleEntity::build(
-                    IdleEntityType::Academic.to_string(),
-                    1350.0,
-                    10_000_000.00,
-                    2.15,
-                ),
-                IdleEntity::build(
-                    IdleEntityType::Catapult.to_string(),
-                    5000.0,
-                    1_000_000_000.00,
-                    3.15,
-                ),
-                IdleEntity::build(
-                    IdleEntityType::King.to_string(),
-                    20_000.0,
-                    1_000_000_000_000.00,
-                    4.0,
-                ),
-            ],
+            init_time_stamp: clock.now(),
+            last_time_stamp: clock.now(),
+            current_gold: Gold::ZERO,
+            idle_entities: catalog
+                .entries()
+                .iter()
+                .map(|entry| IdleEntity::from_catalog_entry(entry, 0))
+                .collect(),
+            resources: BTreeMap::new(),
             current_delta_time: Duration::new(0, 0),
+            lifetime_gold: Gold::ZERO,
+            prestige_points: 0,
+            achievements: Vec::new(),
+            research: Vec::new(),
+            campaign: None,
+            conquered_regions: Vec::new(),
+            edicts: Vec::new(),
+            edicts_changed_at: None,
+            events: EventState::new(clock.now()),
+            managers: Vec::new(),
+            manager_log: Vec::new(),
+            work: WorkState::default(),
+            recent_achievements: Vec::new(),
+            campaign_outcome: None,
+            recent_events: Vec::new(),
+            events_paused: false,
+            recent_manager_purchases: Vec::new(),
+            offline_settings: catalog.offline_settings().clone(),
+            sell_refund_share: catalog.sell_refund_share(),
+            offline_progress: None,
+            offline_cap_lifted: false,
+            clock,
+            clock_skew: None,
         }
     }
 }
 
 // Game methods
 impl Game {
-    pub fn update(&mut self) -> GameState {
-        self.current_delta_time = SystemTime::now()
-            .duration_since(self.last_time_stamp)
-            .unwrap();
+    /// Brings the entities in line with the catalog. Levels of known entities
+    /// are kept, new entities are added and entities which are no longer part
+    /// of the catalog are kept behind the catalog entities.
+    pub fn sync_catalog(&mut self, catalog: &Catalog) {
+        let mut previous_entities = std::mem::take(&mut self.idle_entities);
+
+        for entry in catalog.entries() {
+            let level = previous_entities
+                .iter()
+                .position(|previous| previous.get_name() == entry.name)
+                .map_or(0, |idx| previous_entities.remove(idx).get_level());
+            self.idle_entities
+                .push(IdleEntity::from_catalog_entry(entry, level));
+        }
+        // Only the fresh entities lack the research, the others were saved
+        // with it applied
+        self.apply_research();
+        self.idle_entities.append(&mut previous_entities);
+        self.offline_settings = catalog.offline_settings().clone();
+        self.sell_refund_share = catalog.sell_refund_share();
+        self.apply_edicts();
+    }
 
-        self.update_gold();
-        self.create_game_state();
+    /// Replaces the clock, e.g. to fast-forward a copy of the game
+    pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        self.clock = clock;
+    }
 
-        self.last_time_stamp = SystemTime::now();
+    /// Earns the gold for the time since the last update, with diminishing
+    /// returns for long absences. If the clock went backwards nothing is
+    /// earned and the last update time is kept, so the skewed time is not
+    /// credited twice once the clock is correct again.
+    pub fn update(&mut self) -> GameState {
+        let now = self.clock.now();
+        match now.duration_since(self.last_time_stamp) {
+            Ok(delta_time) => {
+                self.current_delta_time = delta_time;
+                self.clock_skew = None;
+                self.offline_progress = self.offline_progress(delta_time);
+                self.catch_up(now);
+                self.last_time_stamp = now;
+                self.check_campaign();
+                self.check_achievements();
+            }
+            Err(err) => {
+                self.current_delta_time = Duration::ZERO;
+                self.offline_progress = None;
+                self.clock_skew = Some(err.duration());
+            }
+        }
 
         self.create_game_state()
     }
 
-    fn update_gold(&mut self) {
-        for entity in &self.idle_entities {
-            self.current_gold += entity.get_gold(self.current_delta_time.as_millis())
+    /// Earns the gold from the last update until `now`. The interval is
+    /// split wherever an event starts or ends and at every round of the
+    /// managers, so both happen at the time they would have in continuous
+    /// play. Every part earns its credited share of the time and nothing
+    /// happens after the offline cap.
+    fn catch_up(&mut self, now: SystemTime) {
+        let start = self.last_time_stamp;
+        let end = self.offline_end(now);
+        let away_seconds =
+            |time: SystemTime| time.duration_since(start).unwrap_or_default().as_secs_f64();
+        let mut time = start;
+        loop {
+            self.trigger_events(time);
+            // A round at the start was already run by the previous update
+            if time > start {
+                self.run_managers(time);
+            }
+            if time >= end {
+                break;
+            }
+            let segment_end = [self.next_event_change(time), self.next_manager_round(time)]
+                .into_iter()
+                .flatten()
+                .fold(end, |end, change| end.min(change));
+            let credited = self.credited_seconds(away_seconds(segment_end))
+                - self.credited_seconds(away_seconds(time));
+            self.earn_gold(credited);
+            time = segment_end;
+        }
+        if end < now {
+            self.skip_events(now);
         }
     }
 
-    pub fn create_game_state(&self) -> GameState {
-        let total_gold_per_second = self
+    fn earn_gold(&mut self, seconds: f64) {
+        let full_capacity_seconds = self.run_production(seconds);
+        let earned_gold: Gold = self
             .idle_entities
             .iter()
-            .map(|entity| entity.gold_per_second())
-            .sum();
+            .zip(full_capacity_seconds)
+            .enumerate()
+            .map(|(idx, (entity, seconds))| {
+                entity.get_gold(seconds) * self.entity_income_multiplier(idx)
+            })
+            .sum::<Gold>();
+
+        self.current_gold += earned_gold;
+        self.lifetime_gold += earned_gold;
+    }
+
+    /// Multiplier applied to the gold per second of every entity
+    fn income_multiplier(&self) -> f64 {
+        prestige::multiplier(self.prestige_points)
+            * self.achievement_multiplier()
+            * self.research_multiplier()
+            * self.territory_multiplier()
+    }
+
+    /// Multiplier applied to the gold per second of one entity, the only
+    /// place the edicts of the King and the events change the income
+    fn entity_income_multiplier(&self, idx: usize) -> f64 {
+        let name = self.idle_entities[idx].get_name();
+        self.income_multiplier()
+            * self.edict_output_multiplier(name)
+            * self.event_output_multiplier(name)
+    }
+
+    /// Gold per second of the entity at the throughput its resources allow
+    fn entity_gold_per_second(&self, idx: usize, throughput: f64) -> Gold {
+        self.idle_entities[idx].gold_per_second() * self.entity_income_multiplier(idx) * throughput
+    }
+
+    fn total_gold_per_second(&self) -> Gold {
+        self.current_throughputs()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, throughput)| self.entity_gold_per_second(idx, throughput))
+            .sum()
+    }
+
+    /// Gold per second `levels` more levels of the entity add to the total.
+    /// Levels of a consumer short of resources add next to nothing, levels
+    /// of its producer raise the throughput of the consumer as well.
+    fn gold_per_second_gain(&self, idx: usize, levels: u32) -> Gold {
+        let mut upgraded = self.clone();
+        upgraded.idle_entities[idx].add_level(levels);
+        let gain = upgraded.total_gold_per_second() - self.total_gold_per_second();
+        // More demand can throttle other consumers of the same resources
+        if gain > Gold::ZERO {
+            gain
+        } else {
+            Gold::ZERO
+        }
+    }
+
+    pub fn create_game_state(&self) -> GameState {
+        let total_gold_per_second = self.total_gold_per_second();
+        let throughputs = self.current_throughputs();
 
         let game_information = GameInformation {
             gold: self.current_gold,
             gold_per_second: total_gold_per_second,
+            lifetime_gold: self.lifetime_gold,
+            prestige_points: self.prestige_points,
+            prestige_multiplier: prestige::multiplier(self.prestige_points),
+            achievements_unlocked: self.achievements.len(),
+            achievement_multiplier: self.achievement_multiplier(),
+            active_edicts: self.active_edict_names(),
+            army_strength: self.army_strength(),
+            territory_multiplier: self.territory_multiplier(),
+            clock_skew_seconds: self.clock_skew.map(|skew| skew.as_secs_f64()),
+            offline_cap_hours: self.offline_cap_hours(),
+            work_streak: self.work_streak(),
+            work_cooldown_seconds: self.work_cooldown_seconds(),
         };
 
         GameState {
             game_info: game_information,
-            lumberjack_info: self.create_idle_entity_info(0, total_gold_per_second),
-            stonemason_info: self.create_idle_entity_info(1, total_gold_per_second),
-            bowmaker_info: self.create_idle_entity_info(2, total_gold_per_second),
-            weaponsmith_info: self.create_idle_entity_info(3, total_gold_per_second),
-            academic_info: self.create_idle_entity_info(4, total_gold_per_second),
-            catapult_info: self.create_idle_entity_info(5, total_gold_per_second),
-            king_info: self.create_idle_entity_info(6, total_gold_per_second),
+            new_achievements: self.recent_achievements.clone(),
+            entity_infos: throughputs
+                .into_iter()
+                .enumerate()
+                .map(|(idx, throughput)| {
+                    self.create_idle_entity_info(idx, throughput, total_gold_per_second)
+                })
+                .collect(),
+            resources: self.resource_infos(),
+            campaign: self.campaign_info(),
+            campaign_outcome: self.campaign_outcome.clone(),
+            events: self.recent_event_infos(),
+            active_events: self.active_event_infos(),
+            manager_purchases: self.recent_manager_purchases.clone(),
+            offline_progress: self.offline_progress.clone(),
         }
     }
 
     fn create_idle_entity_info(
         &self,
         idx: usize,
-        total_gold_per_second: f64,
+        throughput: f64,
+        total_gold_per_second: Gold,
     ) -> IdleEntityInformation {
-        let upgrade_quanity =
-            self.idle_entities[idx].quanity_of_possible_upgrades(&self.current_gold);
+        let entity = &self.idle_entities[idx];
+        let upgrade_quanity = entity.quanity_of_possible_upgrades(&self.current_gold);
+        let next_milestone = entity.next_milestone().map(|milestone| {
+            let levels_remaining = milestone.level - entity.get_level();
+            MilestoneInformation {
+                level: milestone.level,
+                multiplier: milestone.multiplier,
+                levels_remaining,
+                cost: entity.cost_for_next_upgrades(levels_remaining),
+            }
+        });
 
         IdleEntityInformation {
             name: self.idle_entities[idx].get_name().to_string(),
             level: self.idle_entities[idx].get_level(),
-            gold_per_second: self.idle_entities[idx].gold_per_second(),
-            gold_per_second_percent: self.idle_entities[idx].gold_per_second()
-                / total_gold_per_second,
+            gold_per_second: self.entity_gold_per_second(idx, throughput),
+            gold_per_second_percent: self
+                .entity_gold_per_second(idx, throughput)
+                .ratio(total_gold_per_second),
+            throughput,
             maximum_upgrade_quantity: upgrade_quanity,
             upgrade_cost_next: self.idle_entities[idx].cost_for_next_upgrade(),
             upgrade_cost_max: self.idle_entities[idx].cost_for_next_upgrades(upgrade_quanity),
+            milestone_multiplier: entity.milestone_multiplier(entity.get_level()),
+            next_milestone,
+            sell_refund_next: entity.cost_of_last_levels(1) * self.sell_refund_share,
+            sell_refund_all: entity.cost_of_last_levels(entity.get_level())
+                * self.sell_refund_share,
         }
     }
 
-    pub fn upgrade(&mut self, entity_type: IdleEntityType, amount: u32) -> u32 {
+    /// Upgrades the entity with the given name (ignoring case) up to `amount`
+    /// times and returns the number of successful upgrades
+    pub fn upgrade(&mut self, entity_name: &str, amount: u32) -> u32 {
+        match self.entity_index(entity_name) {
+            Some(idx) => self.upgrade_entity(idx, amount),
+            None => 0,
+        }
+    }
+
+    fn entity_index(&self, entity_name: &str) -> Option<usize> {
+        self.idle_entities
+            .iter()
+            .position(|entity| entity.get_name().eq_ignore_ascii_case(entity_name))
+    }
+
+    fn upgrade_entity(&mut self, idx: usize, amount: u32) -> u32 {
         let mut successful_upgrades = 0;
         while successful_upgrades < amount {
-            let result = match entity_type {
-                IdleEntityType::Lumberjack => self.idle_entities[0].upgrade(&mut self.current_gold),
-                IdleEntityType::Stonemason => self.idle_entities[1].upgrade(&mut self.current_gold),
-                IdleEntityType::Bowmaker => self.idle_entities[2].upgrade(&mut self.current_gold),
-                IdleEntityType::Weaponsmith => {
-                    self.idle_entities[3].upgrade(&mut self.current_gold)
-                }
-                IdleEntityType::Academic => self.idle_entities[4].upgrade(&mut self.current_gold),
-                IdleEntityType::Catapult => self.idle_entities[5].upgrade(&mut self.current_gold),
-                IdleEntityType::King => self.idle_entities[6].upgrade(&mut self.current_gold),
-            };
-
-            if let Err(()) = result {
+            if let Err(()) = self.idle_entities[idx].upgrade(&mut self.current_gold) {
                 break;
             }
 
             successful_upgrades += 1;
         }
+        if successful_upgrades > 0 {
+            self.apply_edicts();
+            self.check_achievements();
+        }
         successful_upgrades
     }
 
+    /// Sells up to `amount` levels of the entity with the given name (ignoring
+    /// case) for a share of what they cost at the current prices
+    pub fn sell(&mut self, entity_name: &str, amount: u32) -> Result<SellSummary, String> {
+        let idx = self
+            .entity_index(entity_name)
+            .ok_or_else(|| format!("Unknown entity {}", entity_name))?;
+        let entity = &mut self.idle_entities[idx];
+        let levels = amount.min(entity.get_level());
+        if levels == 0 {
+            return Err(format!("{} has no levels to sell", entity.get_name()));
+        }
+
+        let refund = entity.cost_of_last_levels(levels) * self.sell_refund_share;
+        entity.remove_levels(levels);
+        self.current_gold += refund;
+        self.apply_edicts();
+
+        let entity = &self.idle_entities[idx];
+        Ok(SellSummary {
+            name: entity.get_name().to_string(),
+            levels,
+            refund,
+            level: entity.get_level(),
+        })
+    }
+
+    /// Spends gold across all entities until nothing is affordable anymore.
+    /// Returns how many levels were bought for each entity and at what cost.
+    pub fn upgrade_all(&mut self, strategy: UpgradeStrategy) -> Vec<UpgradeSummary> {
+        let mut summaries: Vec<UpgradeSummary> = self
+            .idle_entities
+            .iter()
+            .map(|entity| UpgradeSummary {
+                name: entity.get_name().to_string(),
+                levels: 0,
+                gold_spent: Gold::ZERO,
+            })
+            .collect();
+
+        let mut round_robin_idx = 0;
+        while let Some(idx) = self.next_upgrade_candidate(strategy, round_robin_idx) {
+            let cost = self.idle_entities[idx].cost_for_next_upgrade();
+            if self.upgrade_entity(idx, 1) == 0 {
+                break;
+            }
+
+            summaries[idx].levels += 1;
+            summaries[idx].gold_spent += cost;
+            round_robin_idx = (idx + 1) % self.idle_entities.len();
+        }
+        summaries
+    }
+
+    fn next_upgrade_candidate(
+        &self,
+        strategy: UpgradeStrategy,
+        round_robin_idx: usize,
+    ) -> Option<usize> {
+        let entity_count = self.idle_entities.len();
+        let is_affordable =
+            |idx: &usize| self.idle_entities[*idx].cost_for_next_upgrade() <= self.current_gold;
+        let cost = |idx: &usize| self.idle_entities[*idx].cost_for_next_upgrade();
+        let efficiency = |idx: &usize| {
+            self.gold_per_second_gain(*idx, 1)
+                .ratio(self.idle_entities[*idx].cost_for_next_upgrade())
+        };
+
+        match strategy {
+            UpgradeStrategy::Cheapest => (0..entity_count)
+                .filter(is_affordable)
+                .min_by(|a, b| cost(a).partial_cmp(&cost(b)).unwrap_or(Ordering::Equal)),
+            UpgradeStrategy::Efficiency => (0..entity_count)
+                .filter(is_affordable)
+                .max_by(|a, b| efficiency(a).total_cmp(&efficiency(b))),
+            UpgradeStrategy::RoundRobin => (0..entity_count)
+                .map(|offset| (round_robin_idx + offset) % entity_count)
+                .find(is_affordable),
+        }
+    }
+
+    /// Calculates what a prestige reset would pay out without performing it
+    pub fn prestige_preview(&self) -> PrestigeSummary {
+        let total_points = prestige::points_for_lifetime_gold(self.lifetime_gold);
+        let points_gained = total_points.saturating_sub(self.prestige_points);
+
+        PrestigeSummary {
+            lifetime_gold: self.lifetime_gold,
+            points_before: self.prestige_points,
+            points_gained,
+            multiplier_before: prestige::multiplier(self.prestige_points),
+            multiplier_after: prestige::multiplier(self.prestige_points + points_gained),
+            lifetime_gold_for_next_point: prestige::lifetime_gold_for_points(
+                total_points.max(self.prestige_points) + 1,
+            ),
+        }
+    }
+
+    /// Resets all entities and the current gold in exchange for prestige
+    /// points. Nothing is reset if no points would be gained.
+    pub fn prestige(&mut self) -> PrestigeSummary {
+        let summary = self.prestige_preview();
+        if summary.points_gained == 0 {
+            return summary;
+        }
+
+        for entity in &mut self.idle_entities {
+            entity.reset_level();
+        }
+        self.resources.clear();
+        self.apply_edicts();
+        self.current_gold = Gold::ZERO;
+        self.prestige_points += summary.points_gained;
+
+        summary
+    }
+
     pub fn get_delta_time(&self) -> &Duration {
         &self.current_delta_time
     }
 }
+
+/// Fixtures shared by the tests of the game modules
+#[cfg(test)]
+mod test_support {
+    use std::{rc::Rc, time::SystemTime};
+
+    use super::{catalog::Catalog, clock::ManualClock, Game};
+
+    /// A game whose clock starts at the epoch and only moves when advanced
+    pub(super) fn create_game_with_catalog(catalog: &Catalog) -> (Game, Rc<ManualClock>) {
+        let clock = Rc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+        let game = Game::with_clock(catalog, clock.clone());
+        (game, clock)
+    }
+
+    /// `create_game_with_catalog` for the built-in catalog
+    pub(super) fn create_game_with_clock() -> (Game, Rc<ManualClock>) {
+        create_game_with_catalog(&Catalog::builtin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::approx_eq;
+    use std::time::{Duration, SystemTime};
+
+    use super::{
+        catalog::Catalog, test_support::create_game_with_clock, Game, Gold, UpgradeStrategy,
+    };
+
+    fn create_game(gold: f64) -> Game {
+        let mut game = Game::new(&Catalog::builtin());
+        game.current_gold = Gold::from(gold);
+        game
+    }
+
+    #[test]
+    fn upgrade_all_spends_until_nothing_is_affordable() {
+        let mut game = create_game(1_000.0);
+
+        let summaries = game.upgrade_all(UpgradeStrategy::Cheapest);
+
+        let cheapest_cost = game
+            .idle_entities
+            .iter()
+            .map(|entity| entity.cost_for_next_upgrade())
+            .reduce(|cheapest, cost| if cost < cheapest { cost } else { cheapest })
+            .unwrap();
+        assert!(game.current_gold < cheapest_cost);
+
+        let gold_spent: Gold = summaries.iter().map(|summary| summary.gold_spent).sum();
+        assert!(
+            (Gold::from(1_000.0) - gold_spent - game.current_gold)
+                .to_f64()
+                .abs()
+                < 1e-6
+        );
+    }
+
+    #[test]
+    fn upgrade_all_reports_levels_per_entity() {
+        let mut game = create_game(1_000.0);
+
+        let summaries = game.upgrade_all(UpgradeStrategy::Efficiency);
+
+        assert_eq!(game.idle_entities.len(), summaries.len());
+        for (entity, summary) in game.idle_entities.iter().zip(&summaries) {
+            assert_eq!(entity.get_name(), summary.name);
+            assert_eq!(entity.get_level(), summary.levels);
+        }
+    }
+
+    #[test]
+    fn upgrade_all_round_robin_alternates_entities() {
+        // Enough for the first Lumberjack and Stonemason levels, but not for
+        // a second Lumberjack level on top of them
+        let mut game = create_game(11.0);
+
+        let summaries = game.upgrade_all(UpgradeStrategy::RoundRobin);
+
+        assert_eq!(1, summaries[0].levels);
+        assert_eq!(1, summaries[1].levels);
+    }
+
+    #[test]
+    fn upgrade_all_without_gold_buys_nothing() {
+        let mut game = create_game(0.0);
+
+        let summaries = game.upgrade_all(UpgradeStrategy::RoundRobin);
+
+        assert!(summaries.iter().all(|summary| summary.levels == 0));
+    }
+
+    #[test]
+    fn prestige_resets_progress_and_grants_points() {
+        let mut game = create_game(1_000.0);
+        game.upgrade_all(UpgradeStrategy::Cheapest);
+        game.lifetime_gold = Gold::from(4_000_000_000.0);
+
+        let summary = game.prestige();
+
+        assert_eq!(2, summary.points_gained);
+        assert_eq!(2, game.prestige_points);
+        assert_eq!(Gold::ZERO, game.current_gold);
+        assert!(game
+            .idle_entities
+            .iter()
+            .all(|entity| entity.get_level() == 0));
+        assert!(game.income_multiplier() > 1.0);
+    }
+
+    #[test]
+    fn prestige_without_payout_keeps_progress() {
+        let mut game = create_game(1_000.0);
+        game.upgrade("lumberjack", 1);
+        game.lifetime_gold = Gold::from(4_000_000_000.0);
+        game.prestige_points = 2;
+
+        let summary = game.prestige();
+
+        assert_eq!(0, summary.points_gained);
+        assert_eq!(1, game.idle_entities[0].get_level());
+        assert!(game.current_gold > Gold::ZERO);
+    }
+
+    #[test]
+    fn sync_catalog_keeps_levels_and_adds_new_entities() {
+        let mut game = create_game(100.0);
+        game.upgrade("lumberjack", 3);
+
+        let catalog = Catalog::from_json(
+            r#"{"entities": [
+                {"name": "Miner", "base_gold_per_second": 0.5, "base_upgrade_cost": 5.0, "upgrade_cost_factor": 1.2, "unlock_order": 1},
+                {"name": "Lumberjack", "base_gold_per_second": 0.2, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.15, "unlock_order": 0}
+            ]}"#,
+        )
+        .unwrap();
+        game.sync_catalog(&catalog);
+
+        let names: Vec<&str> = game
+            .idle_entities
+            .iter()
+            .map(|entity| entity.get_name())
+            .collect();
+        assert_eq!("Lumberjack", names[0]);
+        assert_eq!("Miner", names[1]);
+        assert_eq!(3, game.idle_entities[0].get_level());
+        assert_eq!(0, game.idle_entities[1].get_level());
+        // Entities missing from the catalog keep their progress
+        assert!(names.contains(&"King"));
+    }
+
+    #[test]
+    fn sell_refunds_a_share_of_the_exact_cost() {
+        let mut game = create_game(1_000.0);
+        game.upgrade("lumberjack", 10);
+        let cost = game.idle_entities[0].cost_of_last_levels(3);
+        let gold_before = game.current_gold;
+
+        let summary = game.sell("lumberjack", 3).unwrap();
+
+        assert_eq!(7, summary.level);
+        assert_eq!(cost * game.sell_refund_share, summary.refund);
+        assert_eq!(gold_before + summary.refund, game.current_gold);
+    }
+
+    #[test]
+    fn sell_is_limited_to_the_levels_owned() {
+        let mut game = create_game(1_000.0);
+        game.upgrade("lumberjack", 2);
+
+        assert_eq!(2, game.sell("lumberjack", 5).unwrap().levels);
+        assert!(game.sell("lumberjack", 1).is_err());
+        assert!(game.sell("dragon", 1).is_err());
+    }
+
+    #[test]
+    fn update_earns_gold_for_elapsed_time() {
+        let (mut game, clock) = create_game_with_clock();
+        let gold_per_second = game.create_game_state().game_info.gold_per_second;
+
+        clock.advance(Duration::from_secs(10));
+        let game_state = game.update();
+
+        assert_eq!(&Duration::from_secs(10), game.get_delta_time());
+        assert!(approx_eq!(
+            f64,
+            (gold_per_second * 10.0).to_f64(),
+            game_state.game_info.gold.to_f64(),
+            ulps = 2
+        ));
+        assert_eq!(None, game_state.game_info.clock_skew_seconds);
+    }
+
+    #[test]
+    fn clock_going_backwards_is_reported_and_earns_nothing() {
+        let (mut game, clock) = create_game_with_clock();
+        let gold_per_second = game.create_game_state().game_info.gold_per_second;
+        game.last_time_stamp = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+
+        let game_state = game.update();
+
+        assert_eq!(Gold::ZERO, game_state.game_info.gold);
+        assert_eq!(Some(60.0), game_state.game_info.clock_skew_seconds);
+
+        // Only the time after the last update is credited once the clock is
+        // correct again
+        clock.advance(Duration::from_secs(70));
+        let game_state = game.update();
+
+        assert!(approx_eq!(
+            f64,
+            (gold_per_second * 10.0).to_f64(),
+            game_state.game_info.gold.to_f64(),
+            ulps = 2
+        ));
+        assert_eq!(None, game_state.game_info.clock_skew_seconds);
+    }
+}
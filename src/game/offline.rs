@@ -0,0 +1,122 @@
+use std::time::{Duration, SystemTime};
+
+use crate::game::{game_state::OfflineProgress, prestige, Game};
+
+const SECONDS_PER_HOUR: f64 = 3_600.0;
+
+impl Game {
+    /// Hours after the last update that still earn, raised by research and
+    /// prestige
+    pub(super) fn offline_cap_hours(&self) -> f64 {
+        self.offline_settings.cap_hours
+            + self.research_offline_hours()
+            + prestige::offline_cap_bonus_hours(self.prestige_points)
+    }
+
+    /// End of the progress credited for an update at `now`
+    pub(super) fn offline_end(&self, now: SystemTime) -> SystemTime {
+        if self.offline_cap_lifted {
+            return now;
+        }
+        let cap = Duration::from_secs_f64(self.offline_cap_hours() * SECONDS_PER_HOUR);
+        self.last_time_stamp
+            .checked_add(cap)
+            .map_or(now, |end| end.min(now))
+    }
+
+    /// Seconds of progress credited for `away_seconds` after the last update.
+    /// The first hours are credited in full, after them the rate falls to
+    /// `1 / (1 + t / falloff)` until the cap is reached.
+    pub(super) fn credited_seconds(&self, away_seconds: f64) -> f64 {
+        if self.offline_cap_lifted {
+            return away_seconds;
+        }
+        let settings = &self.offline_settings;
+        let away = away_seconds.min(self.offline_cap_hours() * SECONDS_PER_HOUR);
+        let full_rate = settings.full_rate_hours * SECONDS_PER_HOUR;
+        if away <= full_rate {
+            return away;
+        }
+        let falloff = settings.falloff_hours * SECONDS_PER_HOUR;
+        full_rate + falloff * ((away - full_rate) / falloff).ln_1p()
+    }
+
+    /// Credits all time in full, e.g. for forecasts of continuous play
+    pub(super) fn lift_offline_cap(&mut self) {
+        self.offline_cap_lifted = true;
+    }
+
+    /// How much of the time away was credited, only reported once the
+    /// returns diminish
+    pub(super) fn offline_progress(&self, away: Duration) -> Option<OfflineProgress> {
+        let away_seconds = away.as_secs_f64();
+        if self.offline_cap_lifted
+            || away_seconds <= self.offline_settings.full_rate_hours * SECONDS_PER_HOUR
+        {
+            return None;
+        }
+        Some(OfflineProgress {
+            away_seconds,
+            credited_seconds: self.credited_seconds(away_seconds),
+            cap_seconds: self.offline_cap_hours() * SECONDS_PER_HOUR,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use float_cmp::approx_eq;
+
+    use crate::game::test_support::create_game_with_clock;
+
+    #[test]
+    fn returns_diminish_after_the_full_rate_and_stop_at_the_cap() {
+        let (game, _) = create_game_with_clock();
+        let hours = |hours: f64| hours * 3_600.0;
+        let settings = game.offline_settings.clone();
+
+        assert_eq!(hours(1.0), game.credited_seconds(hours(1.0)));
+        let after_full_rate = game.credited_seconds(hours(settings.full_rate_hours + 1.0));
+        assert!(after_full_rate > hours(settings.full_rate_hours));
+        assert!(after_full_rate < hours(settings.full_rate_hours + 1.0));
+        assert_eq!(
+            game.credited_seconds(hours(settings.cap_hours)),
+            game.credited_seconds(hours(24.0 * 30.0))
+        );
+    }
+
+    #[test]
+    fn long_absence_earns_the_credited_time() {
+        let (mut game, clock) = create_game_with_clock();
+        game.pause_events();
+        let gold_per_second = game.total_gold_per_second();
+        let away = Duration::from_secs(24 * 3_600);
+
+        clock.advance(away);
+        let game_state = game.update();
+
+        let progress = game_state.offline_progress.unwrap();
+        assert_eq!(away.as_secs_f64(), progress.away_seconds);
+        assert!(progress.credited_seconds < progress.cap_seconds);
+        assert!(approx_eq!(
+            f64,
+            (gold_per_second * progress.credited_seconds).to_f64(),
+            game_state.game_info.gold.to_f64(),
+            epsilon = 1e-6
+        ));
+    }
+
+    #[test]
+    fn research_and_prestige_raise_the_cap() {
+        let (mut game, _) = create_game_with_clock();
+        game.pause_events();
+        let cap_hours = game.offline_cap_hours();
+
+        game.research.push("night_watch".to_string());
+        assert_eq!(cap_hours + 4.0, game.offline_cap_hours());
+        game.prestige_points = 10;
+        assert!(game.offline_cap_hours() > cap_hours + 4.0);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::game::Gold;
+
+/// Lifetime gold needed for the first prestige point
+const GOLD_PER_PRESTIGE_UNIT: f64 = 1_000_000_000.0;
+/// Additional gold per second per prestige point
+const MULTIPLIER_PER_POINT: f64 = 0.02;
+/// Additional hours of progress while away per prestige point
+const OFFLINE_HOURS_PER_POINT: f64 = 0.1;
+
+/// Total prestige points that `lifetime_gold` is worth. Grows with the
+/// square root of the gold, so every point is harder to earn.
+pub fn points_for_lifetime_gold(lifetime_gold: Gold) -> u64 {
+    if lifetime_gold <= Gold::ZERO {
+        return 0;
+    }
+    f64::sqrt((lifetime_gold / GOLD_PER_PRESTIGE_UNIT).to_f64()).floor() as u64
+}
+
+/// Lifetime gold needed to be worth `points` prestige points in total
+pub fn lifetime_gold_for_points(points: u64) -> Gold {
+    Gold::from(points as f64) * (points as f64) * GOLD_PER_PRESTIGE_UNIT
+}
+
+/// Permanent gold per second multiplier granted by `points` prestige points
+pub fn multiplier(points: u64) -> f64 {
+    1.0 + (points as f64) * MULTIPLIER_PER_POINT
+}
+
+/// Hours the prestige points add to the offline cap
+pub fn offline_cap_bonus_hours(points: u64) -> f64 {
+    (points as f64) * OFFLINE_HOURS_PER_POINT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lifetime_gold_for_points, multiplier, points_for_lifetime_gold};
+    use crate::game::Gold;
+
+    #[test]
+    fn no_points_without_gold() {
+        assert_eq!(0, points_for_lifetime_gold(Gold::ZERO));
+        assert_eq!(0, points_for_lifetime_gold(Gold::from(999_999_999.0)));
+    }
+
+    #[test]
+    fn points_match_required_gold() {
+        for points in [1, 2, 10, 250] {
+            assert_eq!(
+                points,
+                points_for_lifetime_gold(lifetime_gold_for_points(points))
+            );
+            assert_eq!(
+                points - 1,
+                points_for_lifetime_gold(lifetime_gold_for_points(points) - Gold::from(1.0))
+            );
+        }
+    }
+
+    #[test]
+    fn multiplier_without_points_is_neutral() {
+        assert_eq!(1.0, multiplier(0));
+    }
+}
@@ -0,0 +1,265 @@
+use std::cmp::Ordering;
+
+use crate::game::{game_state::ResourceInformation, Game};
+
+/// Sum of resource rates. Starts from 0.0, the float `sum` of nothing is -0.0
+/// and prints as such.
+pub(super) fn total_rate(amounts: impl Iterator<Item = f64>) -> f64 {
+    amounts.fold(0.0, |total, amount| total + amount)
+}
+
+impl Game {
+    /// Names of all produced resources, in the order of their producers
+    fn resource_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for (resource, _) in self.idle_entities.iter().filter_map(|e| e.production()) {
+            if !names.iter().any(|name| name == resource) {
+                names.push(resource.to_string());
+            }
+        }
+        names
+    }
+
+    fn stockpile(&self, resource: &str) -> f64 {
+        self.resources.get(resource).copied().unwrap_or(0.0)
+    }
+
+    fn production_per_second(&self, resource: &str, throughputs: &[f64]) -> f64 {
+        let amounts = self
+            .idle_entities
+            .iter()
+            .zip(throughputs)
+            .filter_map(|(entity, throughput)| {
+                let multiplier = self.edict_production_multiplier(entity.get_name());
+                entity
+                    .production()
+                    .map(|(produced, amount)| (produced, amount * multiplier * throughput))
+            })
+            .filter(|(produced, _)| *produced == resource)
+            .map(|(_, amount)| amount);
+        total_rate(amounts)
+    }
+
+    fn consumption_per_second(&self, resource: &str, throughputs: &[f64]) -> f64 {
+        let amounts = self
+            .idle_entities
+            .iter()
+            .zip(throughputs)
+            .map(|(entity, throughput)| entity.consumption(resource) * throughput);
+        total_rate(amounts)
+    }
+
+    /// Share of its capacity every entity runs at if the `depleted`
+    /// resources are limited to their production. A throttled producer makes
+    /// less for the next stage of its chain, so the throttling is repeated
+    /// until the throughputs settle, at most once per entity.
+    fn throughputs(&self, depleted: &[String]) -> Vec<f64> {
+        let mut throughputs = vec![1.0; self.idle_entities.len()];
+        for _ in 0..self.idle_entities.len() {
+            let throttled = self.throttle(depleted, &throughputs);
+            if throttled == throughputs {
+                break;
+            }
+            throughputs = throttled;
+        }
+        throughputs
+    }
+
+    /// Throughputs for producers running at `producer_throughputs`. The
+    /// scarcest resource throttles its consumers first, which lowers the
+    /// demand for the other resources of these consumers.
+    fn throttle(&self, depleted: &[String], producer_throughputs: &[f64]) -> Vec<f64> {
+        let mut throughputs = vec![1.0; self.idle_entities.len()];
+        let mut pending: Vec<&String> = depleted.iter().collect();
+
+        let supply_ratio = |resource: &str, throughputs: &[f64]| {
+            let supply = self.production_per_second(resource, producer_throughputs);
+            let demand = self.consumption_per_second(resource, throughputs);
+            if demand > supply {
+                supply / demand
+            } else {
+                1.0
+            }
+        };
+
+        while let Some((pos, ratio)) = pending
+            .iter()
+            .map(|resource| supply_ratio(resource, &throughputs))
+            .enumerate()
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+        {
+            let resource = pending.swap_remove(pos);
+            for (entity, throughput) in self.idle_entities.iter().zip(&mut throughputs) {
+                if entity.consumption(resource) > 0.0 {
+                    *throughput *= ratio;
+                }
+            }
+        }
+        throughputs
+    }
+
+    /// Throughputs for the current stockpiles
+    pub(super) fn current_throughputs(&self) -> Vec<f64> {
+        let depleted: Vec<String> = self
+            .resource_names()
+            .into_iter()
+            .filter(|resource| self.stockpile(resource) <= 0.0)
+            .collect();
+        self.throughputs(&depleted)
+    }
+
+    /// Produces and consumes resources for `seconds` and returns how many
+    /// seconds every entity ran at full capacity. The interval is split
+    /// whenever a stockpile runs out, from then on the consumers of the
+    /// resource are limited to its production.
+    pub(super) fn run_production(&mut self, seconds: f64) -> Vec<f64> {
+        let resource_names = self.resource_names();
+        let mut depleted: Vec<String> = resource_names
+            .iter()
+            .filter(|resource| self.stockpile(resource) <= 0.0)
+            .cloned()
+            .collect();
+        let mut full_capacity_seconds = vec![0.0; self.idle_entities.len()];
+
+        let mut remaining = seconds;
+        while remaining > 0.0 {
+            let throughputs = self.throughputs(&depleted);
+            let net_rates: Vec<f64> = resource_names
+                .iter()
+                .map(|resource| {
+                    self.production_per_second(resource, &throughputs)
+                        - self.consumption_per_second(resource, &throughputs)
+                })
+                .collect();
+
+            let running_out = resource_names
+                .iter()
+                .zip(&net_rates)
+                .filter(|(resource, net_rate)| **net_rate < 0.0 && !depleted.contains(resource))
+                .map(|(resource, net_rate)| (resource, self.stockpile(resource) / -net_rate))
+                .filter(|(_, seconds_left)| *seconds_left < remaining)
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+            let phase = running_out.map_or(remaining, |(_, seconds_left)| seconds_left);
+
+            for (total, throughput) in full_capacity_seconds.iter_mut().zip(&throughputs) {
+                *total += throughput * phase;
+            }
+            for (resource, net_rate) in resource_names.iter().zip(&net_rates) {
+                let stockpile = (self.stockpile(resource) + net_rate * phase).max(0.0);
+                self.resources.insert(resource.clone(), stockpile);
+            }
+            if let Some((resource, _)) = running_out {
+                self.resources.insert(resource.clone(), 0.0);
+                depleted.push(resource.clone());
+            }
+            remaining -= phase;
+        }
+        full_capacity_seconds
+    }
+
+    pub(super) fn resource_infos(&self) -> Vec<ResourceInformation> {
+        let throughputs = self.current_throughputs();
+        self.resource_names()
+            .into_iter()
+            .map(|resource| ResourceInformation {
+                stockpile: self.stockpile(&resource),
+                production_per_second: self.production_per_second(&resource, &throughputs),
+                consumption_per_second: self.consumption_per_second(&resource, &throughputs),
+                name: resource,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game::{catalog::Catalog, test_support::create_game_with_catalog, Gold};
+
+    // A Smith needs twice the ore a level 0 Miner digs
+    fn smith_catalog() -> Catalog {
+        Catalog::from_json(
+            r#"{"entities": [
+                {"name": "Miner", "base_gold_per_second": 0.0, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.1, "unlock_order": 0,
+                 "produces": {"resource": "ore", "amount": 1.0}},
+                {"name": "Smith", "base_gold_per_second": 10.0, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.1, "unlock_order": 1,
+                 "consumes": [{"resource": "ore", "amount": 2.0}]}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    // The Smelter turns ore into bars, the Smith needs all bars a Smelter
+    // at full capacity makes
+    fn chain_catalog() -> Catalog {
+        Catalog::from_json(
+            r#"{"entities": [
+                {"name": "Miner", "base_gold_per_second": 0.0, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.1, "unlock_order": 0,
+                 "produces": {"resource": "ore", "amount": 1.0}},
+                {"name": "Smelter", "base_gold_per_second": 0.0, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.1, "unlock_order": 1,
+                 "produces": {"resource": "bars", "amount": 2.0},
+                 "consumes": [{"resource": "ore", "amount": 2.0}]},
+                {"name": "Smith", "base_gold_per_second": 10.0, "base_upgrade_cost": 1.0, "upgrade_cost_factor": 1.1, "unlock_order": 2,
+                 "consumes": [{"resource": "bars", "amount": 2.0}]}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn shortfall_throttles_the_whole_chain() {
+        let (mut game, clock) = create_game_with_catalog(&chain_catalog());
+
+        clock.advance(Duration::from_secs(10));
+        let game_state = game.update();
+
+        // The Smelter runs at half capacity and makes half the bars
+        assert_eq!(0.5, game_state.entity_infos[1].throughput);
+        assert_eq!(0.5, game_state.entity_infos[2].throughput);
+        assert_eq!(Gold::from(50.0), game_state.game_info.gold);
+        assert_eq!(1.0, game_state.resources[1].production_per_second);
+        assert_eq!(0.0, game_state.resources[1].stockpile);
+    }
+
+    #[test]
+    fn shortfall_throttles_consumers() {
+        let (mut game, clock) = create_game_with_catalog(&smith_catalog());
+
+        clock.advance(Duration::from_secs(10));
+        let game_state = game.update();
+
+        assert_eq!(0.5, game_state.entity_infos[1].throughput);
+        assert_eq!(Gold::from(50.0), game_state.game_info.gold);
+        assert_eq!(0.0, game_state.resources[0].stockpile);
+        assert_eq!(1.0, game_state.resources[0].consumption_per_second);
+    }
+
+    #[test]
+    fn surplus_is_stocked() {
+        let (mut game, clock) = create_game_with_catalog(&smith_catalog());
+        game.current_gold = Gold::from(10.0);
+        game.upgrade("miner", 3);
+
+        clock.advance(Duration::from_secs(10));
+        let game_state = game.update();
+
+        assert_eq!(1.0, game_state.entity_infos[1].throughput);
+        assert_eq!(20.0, game_state.resources[0].stockpile);
+    }
+
+    #[test]
+    fn consumers_slow_down_once_the_stockpile_runs_out() {
+        let (mut game, clock) = create_game_with_catalog(&smith_catalog());
+        game.resources.insert("ore".to_string(), 10.0);
+
+        // The stockpile lasts 10 seconds at full capacity, the remaining
+        // 20 seconds run at half capacity
+        clock.advance(Duration::from_secs(30));
+        let game_state = game.update();
+
+        assert_eq!(Gold::from(200.0), game_state.game_info.gold);
+        assert_eq!(0.0, game_state.resources[0].stockpile);
+        assert_eq!(0.5, game_state.entity_infos[1].throughput);
+    }
+}
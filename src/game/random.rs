@@ -0,0 +1,55 @@
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+/// Small deterministic number generator (SplitMix64), the same seed always
+/// yields the same numbers, so outcomes stored as a seed can be replayed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Seed for a new outcome, taken from the time it is decided at
+pub fn seed_from_time(time: SystemTime) -> u64 {
+    let nanos = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    Random::new(nanos as u64).next_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Random;
+
+    #[test]
+    fn same_seed_yields_same_numbers() {
+        let mut first = Random::new(42);
+        let mut second = Random::new(42);
+
+        for _ in 0..100 {
+            let number = first.next_f64();
+            assert_eq!(number, second.next_f64());
+            assert!((0.0..1.0).contains(&number));
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use crate::game::{
+    game_state::{RequiredLevel, ResearchNodeInformation},
+    Game,
+};
+
+/// Resource the Academics produce, spent on the nodes of the tech tree
+pub const RESEARCH_RESOURCE: &str = "research";
+
+/// A node of the tech tree, researched once for a permanent effect
+pub struct ResearchNode {
+    /// Key in the save, never change it
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Research points to spend
+    pub cost: f64,
+    /// Nodes which have to be researched first
+    pub prerequisites: &'static [&'static str],
+    /// Entity levels which have to be reached first
+    pub required_levels: &'static [(&'static str, u32)],
+    pub effect: ResearchEffect,
+}
+
+pub enum ResearchEffect {
+    /// Additional gold per second of every entity
+    IncomeBonus(f64),
+    /// Shrinks the share by which the upgrade cost of an entity grows per level
+    CheaperUpgrades {
+        entity: &'static str,
+        reduction: f64,
+    },
+    /// Additional hours of progress credited while away
+    OfflineCap { hours: u32 },
+    /// Unlocks the automation manager of an entity
+    Automation { entity: &'static str },
+}
+
+impl ResearchEffect {
+    fn describe(&self) -> String {
+        match self {
+            ResearchEffect::IncomeBonus(bonus) => {
+                format!("+{:.0}% gold per second", bonus * 100.0)
+            }
+            ResearchEffect::CheaperUpgrades { entity, reduction } => format!(
+                "{} upgrade costs grow {:.0}% slower",
+                entity,
+                reduction * 100.0
+            ),
+            ResearchEffect::OfflineCap { hours } => {
+                format!("{} more hours of progress while away", hours)
+            }
+            ResearchEffect::Automation { entity } => {
+                format!("Unlocks the {} manager", entity)
+            }
+        }
+    }
+}
+
+pub const TECH_TREE: &[ResearchNode] = &[
+    ResearchNode {
+        id: "bookkeeping",
+        name: "Bookkeeping",
+        cost: 50.0,
+        prerequisites: &[],
+        required_levels: &[("Academic", 1)],
+        effect: ResearchEffect::IncomeBonus(0.05),
+    },
+    ResearchNode {
+        id: "sharper_axes",
+        name: "Sharper axes",
+        cost: 200.0,
+        prerequisites: &["bookkeeping"],
+        required_levels: &[("Lumberjack", 25)],
+        effect: ResearchEffect::CheaperUpgrades {
+            entity: "Lumberjack",
+            reduction: 0.2,
+        },
+    },
+    ResearchNode {
+        id: "stone_saws",
+        name: "Stone saws",
+        cost: 300.0,
+        prerequisites: &["bookkeeping"],
+        required_levels: &[("Stonemason", 25)],
+        effect: ResearchEffect::CheaperUpgrades {
+            entity: "Stonemason",
+            reduction: 0.2,
+        },
+    },
+    ResearchNode {
+        id: "night_watch",
+        name: "Night watch",
+        cost: 500.0,
+        prerequisites: &["bookkeeping"],
+        required_levels: &[("Academic", 5)],
+        effect: ResearchEffect::OfflineCap { hours: 4 },
+    },
+    ResearchNode {
+        id: "foremen",
+        name: "Foremen",
+        cost: 1_000.0,
+        prerequisites: &["sharper_axes"],
+        required_levels: &[("Lumberjack", 50)],
+        effect: ResearchEffect::Automation {
+            entity: "Lumberjack",
+        },
+    },
+    ResearchNode {
+        id: "stewards",
+        name: "Stewards",
+        cost: 2_000.0,
+        prerequisites: &["stone_saws", "foremen"],
+        required_levels: &[("Stonemason", 50)],
+        effect: ResearchEffect::Automation {
+            entity: "Stonemason",
+        },
+    },
+    ResearchNode {
+        id: "guilds",
+        name: "Guilds",
+        cost: 2_000.0,
+        prerequisites: &["sharper_axes", "stone_saws"],
+        required_levels: &[("Bowmaker", 25)],
+        effect: ResearchEffect::IncomeBonus(0.1),
+    },
+    ResearchNode {
+        id: "fletchers",
+        name: "Fletchers",
+        cost: 3_000.0,
+        prerequisites: &["guilds"],
+        required_levels: &[("Bowmaker", 50)],
+        effect: ResearchEffect::Automation { entity: "Bowmaker" },
+    },
+    ResearchNode {
+        id: "quartermasters",
+        name: "Quartermasters",
+        cost: 5_000.0,
+        prerequisites: &["guilds", "stewards"],
+        required_levels: &[("Weaponsmith", 10)],
+        effect: ResearchEffect::Automation {
+            entity: "Weaponsmith",
+        },
+    },
+    ResearchNode {
+        id: "royal_archives",
+        name: "Royal archives",
+        cost: 10_000.0,
+        prerequisites: &["night_watch", "guilds"],
+        required_levels: &[("King", 1)],
+        effect: ResearchEffect::OfflineCap { hours: 8 },
+    },
+];
+
+fn find_node(id: &str) -> Option<&'static ResearchNode> {
+    TECH_TREE
+        .iter()
+        .find(|node| node.id.eq_ignore_ascii_case(id))
+}
+
+impl Game {
+    /// Research points available to spend
+    pub fn research_points(&self) -> f64 {
+        self.resources
+            .get(RESEARCH_RESOURCE)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    fn is_researched(&self, node: &ResearchNode) -> bool {
+        self.research.iter().any(|id| id == node.id)
+    }
+
+    fn researched_nodes(&self) -> impl Iterator<Item = &'static ResearchNode> + '_ {
+        TECH_TREE.iter().filter(|node| self.is_researched(node))
+    }
+
+    /// Prerequisites and entity levels the node is still waiting for
+    fn missing_requirements(&self, node: &ResearchNode) -> Vec<String> {
+        let missing_nodes = node
+            .prerequisites
+            .iter()
+            .filter_map(|id| find_node(id))
+            .filter(|prerequisite| !self.is_researched(prerequisite))
+            .map(|prerequisite| prerequisite.name.to_string());
+        let missing_levels = node
+            .required_levels
+            .iter()
+            .filter(|(entity, level)| self.entity_level(entity) < *level)
+            .map(|(entity, level)| format!("{} level {}", entity, level));
+        missing_nodes.chain(missing_levels).collect()
+    }
+
+    /// Spends research points on a node of the tech tree
+    pub fn buy_research(&mut self, id: &str) -> Result<(), String> {
+        let node = find_node(id).ok_or_else(|| format!("Unknown research {}", id))?;
+        if self.is_researched(node) {
+            return Err(format!("{} is already researched", node.name));
+        }
+        let missing = self.missing_requirements(node);
+        if !missing.is_empty() {
+            return Err(format!("{} requires {}", node.name, missing.join(", ")));
+        }
+        let research_points = self.research_points();
+        if research_points < node.cost {
+            return Err(format!(
+                "{} costs {:.0} research points, but only {:.0} are available",
+                node.name, node.cost, research_points
+            ));
+        }
+
+        self.resources
+            .insert(RESEARCH_RESOURCE.to_string(), research_points - node.cost);
+        self.research.push(node.id.to_string());
+        self.apply_research_effect(node);
+        Ok(())
+    }
+
+    /// Applies the effects which change the entities, the others are looked
+    /// up when they are needed
+    fn apply_research_effect(&mut self, node: &ResearchNode) {
+        if let ResearchEffect::CheaperUpgrades { entity, reduction } = node.effect {
+            if let Some(idx) = self.entity_index(entity) {
+                self.idle_entities[idx].reduce_upgrade_cost_factor(reduction);
+            }
+        }
+    }
+
+    /// Applies the research to entities freshly built from the catalog
+    pub(super) fn apply_research(&mut self) {
+        let nodes: Vec<&ResearchNode> = self.researched_nodes().collect();
+        for node in nodes {
+            self.apply_research_effect(node);
+        }
+    }
+
+    /// Gold per second multiplier granted by the researched nodes
+    pub(super) fn research_multiplier(&self) -> f64 {
+        1.0 + self
+            .researched_nodes()
+            .filter_map(|node| match node.effect {
+                ResearchEffect::IncomeBonus(bonus) => Some(bonus),
+                _ => None,
+            })
+            .sum::<f64>()
+    }
+
+    /// Hours the researched nodes add to the offline cap
+    pub(super) fn research_offline_hours(&self) -> f64 {
+        self.researched_nodes()
+            .map(|node| match node.effect {
+                ResearchEffect::OfflineCap { hours } => hours as f64,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    /// Entities whose manager was unlocked by the researched nodes
+    pub(super) fn automated_entities(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.researched_nodes()
+            .filter_map(|node| match node.effect {
+                ResearchEffect::Automation { entity } => Some(entity),
+                _ => None,
+            })
+    }
+
+    /// Looks up a node by its id, ignoring case
+    pub fn research_info(&self, id: &str) -> Option<ResearchNodeInformation> {
+        find_node(id).map(|node| self.create_research_info(node))
+    }
+
+    /// All nodes of the tech tree, in the order they are unlocked
+    pub fn research_infos(&self) -> Vec<ResearchNodeInformation> {
+        TECH_TREE
+            .iter()
+            .map(|node| self.create_research_info(node))
+            .collect()
+    }
+
+    fn create_research_info(&self, node: &ResearchNode) -> ResearchNodeInformation {
+        ResearchNodeInformation {
+            id: node.id.to_string(),
+            name: node.name.to_string(),
+            description: node.effect.describe(),
+            cost: node.cost,
+            prerequisites: node.prerequisites.iter().map(|id| id.to_string()).collect(),
+            required_levels: node
+                .required_levels
+                .iter()
+                .map(|(entity, level)| RequiredLevel {
+                    entity: entity.to_string(),
+                    level: *level,
+                })
+                .collect(),
+            researched: self.is_researched(node),
+            missing_requirements: self.missing_requirements(node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{RESEARCH_RESOURCE, TECH_TREE};
+    use crate::{
+        game::{catalog::Catalog, Game, Gold},
+        save,
+    };
+
+    fn create_game(research_points: f64) -> Game {
+        let mut game = Game::new(&Catalog::builtin());
+        game.resources
+            .insert(RESEARCH_RESOURCE.to_string(), research_points);
+        game
+    }
+
+    #[test]
+    fn tech_tree_ids_are_unique_and_prerequisites_exist() {
+        let ids: HashSet<&str> = TECH_TREE.iter().map(|node| node.id).collect();
+
+        assert_eq!(TECH_TREE.len(), ids.len());
+        // Prerequisites come first, so the tree has no cycles
+        for (idx, node) in TECH_TREE.iter().enumerate() {
+            for prerequisite in node.prerequisites {
+                assert!(TECH_TREE[..idx]
+                    .iter()
+                    .any(|other| other.id == *prerequisite));
+            }
+        }
+    }
+
+    #[test]
+    fn research_needs_prerequisites_levels_and_points() {
+        let mut game = create_game(40.0);
+
+        assert!(game.buy_research("sharper_axes").is_err());
+        assert!(game.buy_research("bookkeeping").is_err());
+
+        game.current_gold = Gold::from(1e8);
+        game.upgrade("academic", 1);
+        // Not enough research points
+        assert!(game.buy_research("bookkeeping").is_err());
+
+        game.resources.insert(RESEARCH_RESOURCE.to_string(), 60.0);
+        let multiplier_before = game.income_multiplier();
+        assert!(game.buy_research("Bookkeeping").is_ok());
+
+        assert_eq!(10.0, game.research_points());
+        assert!(game.income_multiplier() > multiplier_before);
+        assert!(game.buy_research("bookkeeping").is_err());
+        assert_eq!(
+            vec!["Lumberjack level 25".to_string()],
+            game.research_info("sharper_axes")
+                .unwrap()
+                .missing_requirements
+        );
+    }
+
+    #[test]
+    fn cheaper_upgrades_survive_a_catalog_sync() {
+        let mut game = create_game(1_000.0);
+        game.current_gold = Gold::from(1e8);
+        game.upgrade("academic", 1);
+        game.upgrade("lumberjack", 25);
+        let cost_before = game.idle_entities[0].cost_for_next_upgrade();
+
+        game.buy_research("bookkeeping").unwrap();
+        game.buy_research("sharper_axes").unwrap();
+        let cost_after = game.idle_entities[0].cost_for_next_upgrade();
+        assert!(cost_after < cost_before);
+
+        game.sync_catalog(&Catalog::builtin());
+        assert_eq!(cost_after, game.idle_entities[0].cost_for_next_upgrade());
+    }
+
+    #[test]
+    fn cheaper_upgrades_apply_once_to_entities_missing_from_the_catalog() {
+        let mut game = create_game(1_000.0);
+        game.current_gold = Gold::from(1e8);
+        game.upgrade("academic", 1);
+        game.upgrade("lumberjack", 25);
+        game.buy_research("bookkeeping").unwrap();
+        game.buy_research("sharper_axes").unwrap();
+        let cost = game.idle_entities[0].cost_for_next_upgrade();
+
+        // The Lumberjack is kept from the save behind the catalog entities
+        let catalog = Catalog::from_json(
+            r#"{"entities": [
+                {"name": "Academic", "base_gold_per_second": 1350.0, "base_upgrade_cost": 1e7, "upgrade_cost_factor": 2.15, "unlock_order": 0}
+            ]}"#,
+        )
+        .unwrap();
+        let reload = |game: &Game| {
+            let mut loaded = save::from_document(save::to_document(game).unwrap()).unwrap();
+            loaded.sync_catalog(&catalog);
+            loaded
+        };
+        let loaded = reload(&game);
+        let reloaded = reload(&loaded);
+
+        assert_eq!("Lumberjack", loaded.idle_entities[1].get_name());
+        assert_eq!(cost, loaded.idle_entities[1].cost_for_next_upgrade());
+        assert_eq!(cost, reloaded.idle_entities[1].cost_for_next_upgrade());
+    }
+}
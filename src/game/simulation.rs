@@ -0,0 +1,138 @@
+use std::{rc::Rc, time::Duration};
+
+use crate::game::{
+    clock::ManualClock,
+    game_state::{SimulationReport, SimulationStep},
+    Game, Gold, UpgradeStrategy,
+};
+
+/// Upper bound of simulated steps, longer simulations use longer steps
+const MAX_SIMULATION_STEPS: u32 = 100_000;
+
+impl Game {
+    /// Simulates the game for `duration` on a copy of it. After every `step`
+    /// all gold is spent with `strategy`, or saved up without a strategy.
+    pub fn simulate(
+        &self,
+        duration: Duration,
+        step: Duration,
+        strategy: Option<UpgradeStrategy>,
+    ) -> SimulationReport {
+        let mut game = self.clone();
+        let clock = Rc::new(ManualClock::new(game.last_time_stamp));
+        game.set_clock(clock.clone());
+        // Events are left out, the forecast shows the steady income of
+        // continuous play, however long the steps are
+        game.pause_events();
+        game.lift_offline_cap();
+        let step = step.max(duration / MAX_SIMULATION_STEPS);
+        let lifetime_gold_before = game.lifetime_gold;
+
+        let mut timeline = Vec::new();
+        let mut gold_spent = Gold::ZERO;
+        let mut elapsed = Duration::ZERO;
+        while elapsed < duration {
+            let delta_time = step.min(duration - elapsed);
+            clock.advance(delta_time);
+            game.update();
+            elapsed += delta_time;
+
+            let Some(strategy) = strategy else {
+                continue;
+            };
+            let upgrades: Vec<_> = game
+                .upgrade_all(strategy)
+                .into_iter()
+                .filter(|summary| summary.levels > 0)
+                .collect();
+            if upgrades.is_empty() {
+                continue;
+            }
+
+            gold_spent += upgrades
+                .iter()
+                .map(|summary| summary.gold_spent)
+                .sum::<Gold>();
+            timeline.push(SimulationStep {
+                elapsed_seconds: elapsed.as_secs(),
+                upgrades,
+                gold_per_second_after: game.total_gold_per_second(),
+            });
+        }
+
+        SimulationReport {
+            duration_seconds: duration.as_secs(),
+            gold_earned: game.lifetime_gold - lifetime_gold_before,
+            gold_spent,
+            timeline,
+            final_state: game.create_game_state(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game::{catalog::Catalog, Game, Gold, UpgradeStrategy};
+
+    #[test]
+    fn simulation_without_strategy_only_saves_gold() {
+        let game = Game::new(&Catalog::builtin());
+        let gold_per_second = game.total_gold_per_second();
+
+        let report = game.simulate(Duration::from_secs(100), Duration::from_secs(10), None);
+
+        assert!(report.timeline.is_empty());
+        assert_eq!(Gold::ZERO, report.gold_spent);
+        let expected_gold = (gold_per_second * 100.0).to_f64();
+        assert!((report.final_state.game_info.gold.to_f64() - expected_gold).abs() < 1e-6);
+    }
+
+    #[test]
+    fn long_steps_are_not_capped_like_time_away() {
+        let game = Game::new(&Catalog::builtin());
+        let gold_per_second = game.total_gold_per_second();
+        let duration = Duration::from_secs(7 * 24 * 3_600);
+
+        let report = game.simulate(duration, Duration::from_secs(12 * 3_600), None);
+
+        // Achievements unlocked on the way can only add to the income
+        assert!(report.gold_earned >= gold_per_second * duration.as_secs_f64() * 0.999_999);
+        assert!(report.final_state.offline_progress.is_none());
+    }
+
+    #[test]
+    fn simulation_with_strategy_buys_upgrades() {
+        let game = Game::new(&Catalog::builtin());
+
+        let report = game.simulate(
+            Duration::from_secs(3_600),
+            Duration::from_secs(60),
+            Some(UpgradeStrategy::Cheapest),
+        );
+
+        assert!(!report.timeline.is_empty());
+        assert!(report.gold_spent > Gold::ZERO);
+        assert!(report.final_state.game_info.gold_per_second > game.total_gold_per_second());
+    }
+
+    #[test]
+    fn simulation_does_not_change_the_game() {
+        let game = Game::new(&Catalog::builtin());
+        let last_time_stamp = game.last_time_stamp;
+
+        game.simulate(
+            Duration::from_secs(600),
+            Duration::from_secs(60),
+            Some(UpgradeStrategy::Efficiency),
+        );
+
+        assert_eq!(last_time_stamp, game.last_time_stamp);
+        assert_eq!(Gold::ZERO, game.current_gold);
+        assert!(game
+            .idle_entities
+            .iter()
+            .all(|entity| entity.get_level() == 0));
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{
+    game_state::WorkSummary,
+    random::{self, Random},
+    Game, Gold,
+};
+
+/// Seconds of the total gold per second one work pays
+const WORK_INCOME_SECONDS: f64 = 60.0;
+
+/// Shortest cooldown after a work, a random share of the jitter is added so
+/// the next work cannot be timed to the second. A loop that sleeps longer
+/// than both never runs into the penalty, the daily limit caps what it earns.
+const WORK_COOLDOWN: Duration = Duration::from_secs(4 * 60);
+const WORK_COOLDOWN_JITTER: Duration = Duration::from_secs(2 * 60);
+
+/// Time after the cooldown in which the next work continues the streak
+const COMBO_WINDOW: Duration = Duration::from_secs(30 * 60);
+
+/// Additional pay for every work of the streak after the first
+const COMBO_BONUS_PER_STEP: f64 = 0.1;
+const MAX_COMBO: u32 = 10;
+
+/// Added to the cooldown by every attempt to work before it ran out
+const SPAM_PENALTY: Duration = Duration::from_secs(60);
+
+/// Paid works within a day, once they are used up the cooldown lasts until
+/// the oldest of them is a day old
+const MAX_WORKS_PER_DAY: usize = 24;
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Cooldown and combo streak of the work command, as stored in the save
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WorkState {
+    /// Earliest time of the next work
+    available_at: Option<SystemTime>,
+    /// Works in a row, each within the combo window of the previous one
+    streak: u32,
+    /// Times of the paid works of the last day, oldest first
+    recent_works: Vec<SystemTime>,
+}
+
+fn combo_multiplier(streak: u32) -> f64 {
+    1.0 + streak.clamp(1, MAX_COMBO).saturating_sub(1) as f64 * COMBO_BONUS_PER_STEP
+}
+
+impl Game {
+    fn work_cooldown(&self) -> Duration {
+        self.work
+            .available_at
+            .map_or(Duration::ZERO, |available_at| {
+                available_at
+                    .duration_since(self.clock.now())
+                    .unwrap_or_default()
+            })
+    }
+
+    /// Works for a share of the income. Working during the cooldown pays
+    /// nothing, breaks the streak and makes the cooldown longer.
+    pub fn work(&mut self) -> WorkSummary {
+        let now = self.clock.now();
+        if let Some(available_at) = self.work.available_at.filter(|at| now < *at) {
+            self.work.available_at = Some(available_at + SPAM_PENALTY);
+            self.work.streak = 0;
+            return WorkSummary {
+                too_early: true,
+                gold_earned: Gold::ZERO,
+                streak: 0,
+                combo_multiplier: combo_multiplier(0),
+                cooldown_seconds: self.work_cooldown().as_secs(),
+                penalty_seconds: SPAM_PENALTY.as_secs(),
+                works_left_today: self.works_left_today(now),
+            };
+        }
+
+        let continues_streak = self
+            .work
+            .available_at
+            .is_some_and(|available_at| now <= available_at + COMBO_WINDOW);
+        self.work.streak = if continues_streak {
+            (self.work.streak + 1).min(MAX_COMBO)
+        } else {
+            1
+        };
+
+        let multiplier = combo_multiplier(self.work.streak);
+        let gold_earned = self.total_gold_per_second() * (WORK_INCOME_SECONDS * multiplier);
+        self.current_gold += gold_earned;
+        self.lifetime_gold += gold_earned;
+
+        self.work.recent_works.retain(|at| *at + DAY > now);
+        self.work.recent_works.push(now);
+        let jitter = Random::new(random::seed_from_time(now)).next_f64();
+        let mut available_at = now + WORK_COOLDOWN + WORK_COOLDOWN_JITTER.mul_f64(jitter);
+        if self.work.recent_works.len() >= MAX_WORKS_PER_DAY {
+            available_at = available_at.max(self.work.recent_works[0] + DAY);
+        }
+        self.work.available_at = Some(available_at);
+        self.check_achievements();
+
+        WorkSummary {
+            too_early: false,
+            gold_earned,
+            streak: self.work.streak,
+            combo_multiplier: multiplier,
+            cooldown_seconds: self.work_cooldown().as_secs(),
+            penalty_seconds: 0,
+            works_left_today: self.works_left_today(now),
+        }
+    }
+
+    /// Paid works left within the day before `now`
+    fn works_left_today(&self, now: SystemTime) -> u32 {
+        let works_today = self
+            .work
+            .recent_works
+            .iter()
+            .filter(|at| **at + DAY > now)
+            .count();
+        MAX_WORKS_PER_DAY.saturating_sub(works_today) as u32
+    }
+
+    /// Streak a work right now would continue, 0 once it is broken
+    pub(super) fn work_streak(&self) -> u32 {
+        match self.work.available_at {
+            Some(available_at) if self.clock.now() <= available_at + COMBO_WINDOW => {
+                self.work.streak
+            }
+            _ => 0,
+        }
+    }
+
+    pub(super) fn work_cooldown_seconds(&self) -> u64 {
+        self.work_cooldown().as_secs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use float_cmp::approx_eq;
+
+    use super::{COMBO_WINDOW, DAY, MAX_WORKS_PER_DAY, WORK_COOLDOWN, WORK_COOLDOWN_JITTER};
+    use crate::game::{test_support::create_game_with_clock, Gold};
+
+    #[test]
+    fn work_pays_income_and_starts_the_cooldown() {
+        let (mut game, _) = create_game_with_clock();
+        let gold_per_second = game.total_gold_per_second();
+
+        let summary = game.work();
+
+        assert!(!summary.too_early);
+        assert_eq!(gold_per_second * 60.0, summary.gold_earned);
+        assert_eq!(summary.gold_earned, game.current_gold);
+        assert!(summary.cooldown_seconds >= WORK_COOLDOWN.as_secs());
+        assert!(summary.cooldown_seconds <= (WORK_COOLDOWN + WORK_COOLDOWN_JITTER).as_secs());
+    }
+
+    #[test]
+    fn working_early_is_penalized() {
+        let (mut game, clock) = create_game_with_clock();
+        let first = game.work();
+
+        clock.advance(Duration::from_secs(10));
+        let summary = game.work();
+
+        assert!(summary.too_early);
+        assert_eq!(Gold::ZERO, summary.gold_earned);
+        assert_eq!(first.cooldown_seconds - 10 + 60, summary.cooldown_seconds);
+        assert_eq!(0, game.work_streak());
+    }
+
+    #[test]
+    fn regular_work_builds_the_combo() {
+        let (mut game, clock) = create_game_with_clock();
+        let cooldown = WORK_COOLDOWN + WORK_COOLDOWN_JITTER;
+
+        for streak in 1..=3 {
+            let summary = game.work();
+            assert_eq!(streak, summary.streak);
+            clock.advance(cooldown);
+        }
+        let summary = game.work();
+        assert_eq!(4, summary.streak);
+        assert!(approx_eq!(f64, 1.3, summary.combo_multiplier, ulps = 2));
+
+        // A long break starts a new streak
+        clock.advance(cooldown + COMBO_WINDOW);
+        assert_eq!(0, game.work_streak());
+        assert_eq!(1, game.work().streak);
+    }
+
+    #[test]
+    fn a_loop_only_earns_the_daily_limit() {
+        let (mut game, clock) = create_game_with_clock();
+        // Sleeps through the longest cooldown, so it is never too early
+        // before the limit
+        let sleep = WORK_COOLDOWN + WORK_COOLDOWN_JITTER;
+
+        let mut paid_works = 0;
+        for _ in 0..DAY.as_secs() / sleep.as_secs() {
+            let summary = game.work();
+            if !summary.too_early {
+                paid_works += 1;
+                assert_eq!(
+                    MAX_WORKS_PER_DAY - paid_works,
+                    summary.works_left_today as usize
+                );
+            }
+            clock.advance(sleep);
+        }
+
+        assert_eq!(MAX_WORKS_PER_DAY, paid_works);
+    }
+}
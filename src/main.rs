@@ -1,133 +1,403 @@
 mod args;
 mod config;
 mod game;
+mod output;
+mod play;
+mod profile;
+mod save;
 mod tui;
 
-use std::{io, process};
+use std::{io, process, time::Duration};
 
-use args::CaruArgs;
+use args::{CaruArgs, IdleEntityAmendmentArg};
 use clap::Parser;
 use config::Config;
-use game::IdleEntityType;
+use game::{
+    game_state::{IdleEntityInformation, ResearchNodeInformation, UpgradeResult},
+    manager::ManagerRule,
+    Game, UpgradeStrategy,
+};
 
-fn main() -> io::Result<()> {
+fn main() {
     let home_dir = dirs::home_dir().unwrap_or_else(|| {
         eprintln!("Cannot find home directory.");
         process::exit(1)
     });
 
-    let config = Config::build(&home_dir);
     let cli = CaruArgs::parse();
 
+    let result =
+        Config::build(&home_dir, cli.profile.as_deref()).and_then(|config| run(cli, &config));
+    match result {
+        // The reader of a pipe stopped early, like `head` does
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            process::exit(1)
+        }
+        Ok(()) => {}
+    }
+}
+
+fn run(cli: CaruArgs, config: &Config) -> io::Result<()> {
+    let format = cli.output;
+    let created_game_directory =
+        matches!(cli.command, args::BasicCommand::Init) && config::create_game_directory(config)?;
+
+    // Held until the command is done, so concurrent invocations cannot
+    // overwrite each other's progress
+    let _game_lock = match cli.command {
+        // Waits for running commands before their directory is removed
+        args::BasicCommand::Delete(args::DeleteArgs { all: true }) => {
+            Some(config.lock(Duration::from_secs(cli.lock_timeout))?)
+        }
+        // Locks the game only while saving, the session runs for a long time
+        args::BasicCommand::Play => {
+            let _game_lock = config.lock(Duration::from_secs(cli.lock_timeout))?;
+            config.adopt_legacy_save()?;
+            None
+        }
+        _ => {
+            let game_lock = config.lock(Duration::from_secs(cli.lock_timeout))?;
+            config.adopt_legacy_save()?;
+            Some(game_lock)
+        }
+    };
+
     match cli.command {
-        args::BasicCommand::Init => config::init_game(&config),
-        args::BasicCommand::Delete => config::delete_game_directory(&config),
+        args::BasicCommand::Init => {
+            config::init_game(config)?;
+            output::init(format, config, created_game_directory)
+        }
+        args::BasicCommand::Delete(delete_args) => {
+            if delete_args.all {
+                config::delete_game_directory(config)?;
+            } else {
+                config::delete_profile(config)?;
+            }
+            output::delete(format, config, delete_args.all)
+        }
+        args::BasicCommand::Profile(profile_args) => match profile_args.command {
+            args::ProfileCommand::List => {
+                output::profiles(format, &profile::list_profiles(config)?, &config.profile)
+            }
+            args::ProfileCommand::Create { name } => {
+                let profile_config = config.with_profile(&name)?;
+                config::init_game(&profile_config)?;
+                output::init(format, &profile_config, false)
+            }
+            args::ProfileCommand::Copy { source, target } => {
+                profile::copy_profile(config, &source, &target)?;
+                output::profile_copy(format, &source, &target)
+            }
+            args::ProfileCommand::Delete { name } => {
+                let profile_config = config.with_profile(&name)?;
+                config::delete_profile(&profile_config)?;
+                output::delete(format, &profile_config, false)
+            }
+            args::ProfileCommand::Switch { name } => {
+                profile::switch_profile(config, &name)?;
+                output::profile_switch(format, &name)
+            }
+        },
         args::BasicCommand::Status => {
-            let mut game = config.load()?;
+            let mut game = config.load(&config.load_catalog()?)?;
 
             let game_state = game.update();
-            tui::display_status(&game_state, game.get_delta_time());
+            output::status(format, &game_state, game.get_delta_time())?;
+
+            config.save(game)
+        }
+        args::BasicCommand::Work => {
+            let mut game = config.load(&config.load_catalog()?)?;
+            game.update();
+
+            let summary = game.work();
+            output::work(format, &summary)?;
 
             config.save(game)
         }
+        args::BasicCommand::Play => {
+            if format != args::OutputFormat::Text {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "The play mode only supports text output",
+                ));
+            }
+            play::play(config, Duration::from_secs(cli.lock_timeout))
+        }
         args::BasicCommand::Upgrade(entity_args) => {
-            let mut game = config.load()?;
+            let mut game = config.load(&config.load_catalog()?)?;
             let game_state = game.update();
 
             match entity_args.entity {
-                args::IdleEntityArg::All => panic!("Upgrade all is not implemented"),
-                args::IdleEntityArg::Lumberjack(amendment_arg) => {
-                    if let Some(entity_count_arg) = amendment_arg.count {
-                        let successful_upgrades = game.upgrade(
-                            IdleEntityType::Lumberjack,
-                            get_amount_from_entity_count_arg(&entity_count_arg),
-                        );
-                        tui::confirm_upgrade_info(
-                            successful_upgrades,
-                            get_amount_from_entity_count_arg(&entity_count_arg),
-                            &game_state.lumberjack_info.name,
-                        );
-                    };
-                    if amendment_arg.info {
-                        tui::display_upgrade_info(&game_state.lumberjack_info, &game_state)
-                    };
+                args::IdleEntityArg::All(upgrade_all_arg) => {
+                    let summaries = game.upgrade_all(get_strategy_from_spending_strategy_arg(
+                        &upgrade_all_arg.strategy,
+                    ));
+                    output::upgrade_all(format, &summaries)?;
                 }
-                args::IdleEntityArg::Stonemason(amendment_arg) => {
-                    if let Some(entity_count_arg) = amendment_arg.count {
-                        game.upgrade(
-                            IdleEntityType::Stonemason,
-                            get_amount_from_entity_count_arg(&entity_count_arg),
-                        );
-                    };
-                    if amendment_arg.info {
-                        tui::display_upgrade_info(&game_state.stonemason_info, &game_state)
-                    };
+                args::IdleEntityArg::Entity(entity_command) => {
+                    let (entity_name, amendment_arg) =
+                        IdleEntityAmendmentArg::parse_entity_command(&entity_command)
+                            .unwrap_or_else(|err| err.exit());
+                    let entity_info =
+                        game_state
+                            .find_entity_info(&entity_name)
+                            .unwrap_or_else(|| {
+                                eprintln!(
+                                    "Unknown entity {}. Available entities: {}",
+                                    entity_name,
+                                    tui::entity_names(&game_state)
+                                );
+                                process::exit(1)
+                            });
+
+                    let upgrade_result = amendment_arg
+                        .count
+                        .map(|entity_count_arg| {
+                            let amount =
+                                get_amount_from_entity_count_arg(&entity_count_arg, entity_info)?;
+                            Ok::<_, io::Error>(UpgradeResult {
+                                name: entity_info.name.clone(),
+                                requested: amount,
+                                successful: game.upgrade(&entity_info.name, amount),
+                            })
+                        })
+                        .transpose()?;
+                    output::upgrade_entity(
+                        format,
+                        upgrade_result.as_ref(),
+                        amendment_arg.info.then_some(entity_info),
+                        &game_state,
+                    )?;
                 }
-                args::IdleEntityArg::Bowmaker(amendment_arg) => {
-                    if let Some(entity_count_arg) = amendment_arg.count {
-                        game.upgrade(
-                            IdleEntityType::Bowmaker,
-                            get_amount_from_entity_count_arg(&entity_count_arg),
-                        );
-                    };
-                    if amendment_arg.info {
-                        tui::display_upgrade_info(&game_state.bowmaker_info, &game_state)
-                    };
+            }
+            config.save(game)
+        }
+        args::BasicCommand::Sell(sell_args) => {
+            let mut game = config.load(&config.load_catalog()?)?;
+            game.update();
+
+            let summary = game
+                .sell(&sell_args.entity, sell_args.amount)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            output::sell(format, &summary)?;
+
+            config.save(game)
+        }
+        args::BasicCommand::Advise(advise_args) => {
+            let mut game = config.load(&config.load_catalog()?)?;
+            game.update();
+
+            let advice = game.advise(advise_args.levels);
+            let plan = advise_args
+                .horizon
+                .map(|horizon| (game.plan_purchases(horizon), horizon));
+            output::advice(
+                format,
+                &advice,
+                plan.as_ref()
+                    .map(|(plan, horizon)| (plan.as_slice(), horizon)),
+            )?;
+
+            config.save(game)
+        }
+        args::BasicCommand::Simulate(simulate_args) => {
+            let mut game = config.load(&config.load_catalog()?)?;
+            game.update();
+
+            let report = game.simulate(
+                simulate_args.duration,
+                simulate_args.step,
+                get_strategy_from_simulation_strategy_arg(&simulate_args.strategy),
+            );
+            // The simulation is only a forecast, the save stays as it is
+            output::simulation(format, &report)
+        }
+        args::BasicCommand::Achievements => {
+            let mut game = config.load(&config.load_catalog()?)?;
+            let game_state = game.update();
+
+            output::achievements(format, &game.achievement_infos(), &game_state)?;
+
+            config.save(game)
+        }
+        args::BasicCommand::Research(research_args) => {
+            let mut game = config.load(&config.load_catalog()?)?;
+            game.update();
+
+            match research_args.command {
+                args::ResearchCommand::List => {
+                    output::research(format, &game.research_infos(), game.research_points())?
                 }
-                args::IdleEntityArg::Weaponsmith(amendment_arg) => {
-                    if let Some(entity_count_arg) = amendment_arg.count {
-                        game.upgrade(
-                            IdleEntityType::Weaponsmith,
-                            get_amount_from_entity_count_arg(&entity_count_arg),
-                        );
-                    };
-                    if amendment_arg.info {
-                        tui::display_upgrade_info(&game_state.weaponsmith_info, &game_state)
-                    };
+                args::ResearchCommand::Info { node } => output::research_node(
+                    format,
+                    &find_research_info(&game, &node)?,
+                    game.research_points(),
+                    false,
+                )?,
+                args::ResearchCommand::Buy { node } => {
+                    game.buy_research(&node)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+                    output::research_node(
+                        format,
+                        &find_research_info(&game, &node)?,
+                        game.research_points(),
+                        true,
+                    )?
+                }
+            }
+
+            config.save(game)
+        }
+        args::BasicCommand::Campaign(campaign_args) => {
+            let mut game = config.load(&config.load_catalog()?)?;
+            let game_state = game.update();
+
+            match campaign_args.command {
+                args::CampaignCommand::List => {
+                    output::regions(format, &game.region_infos(), &game_state)?
                 }
-                args::IdleEntityArg::Academic(amendment_arg) => {
-                    if let Some(entity_count_arg) = amendment_arg.count {
-                        game.upgrade(
-                            IdleEntityType::Academic,
-                            get_amount_from_entity_count_arg(&entity_count_arg),
-                        );
-                    };
-                    if amendment_arg.info {
-                        tui::display_upgrade_info(&game_state.academic_info, &game_state)
-                    };
+                args::CampaignCommand::Start { region } => {
+                    let campaign = game
+                        .start_campaign(&region)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+                    output::campaign(format, &campaign)?
                 }
-                args::IdleEntityArg::Catapult(amendment_arg) => {
-                    if let Some(entity_count_arg) = amendment_arg.count {
-                        game.upgrade(
-                            IdleEntityType::Catapult,
-                            get_amount_from_entity_count_arg(&entity_count_arg),
-                        );
-                    };
-                    if amendment_arg.info {
-                        tui::display_upgrade_info(&game_state.catapult_info, &game_state)
-                    };
+            }
+
+            config.save(game)
+        }
+        args::BasicCommand::Edict(edict_args) => {
+            let mut game = config.load(&config.load_catalog()?)?;
+            game.update();
+
+            let changed = match edict_args.command {
+                args::EdictCommand::List => None,
+                args::EdictCommand::Set { edict, slot } => {
+                    Some(game.set_edict(&edict, slot).map(|_| edict))
                 }
-                args::IdleEntityArg::King(amendment_arg) => {
-                    if let Some(entity_count_arg) = amendment_arg.count {
-                        game.upgrade(
-                            IdleEntityType::King,
-                            get_amount_from_entity_count_arg(&entity_count_arg),
-                        );
-                    };
-                    if amendment_arg.info {
-                        tui::display_upgrade_info(&game_state.king_info, &game_state)
-                    };
+                args::EdictCommand::Clear { edict } => {
+                    Some(game.clear_edict(&edict).map(|_| edict))
                 }
             }
+            .transpose()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            output::edicts(format, &game.edict_overview(), changed.as_deref())?;
+
             config.save(game)
         }
+        args::BasicCommand::Manager(manager_args) => {
+            let mut game = config.load(&config.load_catalog()?)?;
+            game.update();
+
+            let changed = match manager_args.command {
+                args::ManagerCommand::List => None,
+                args::ManagerCommand::Keep { entity, within, of } => Some(
+                    game.add_manager_rule(ManagerRule::KeepWithin {
+                        entity: entity.clone(),
+                        other: of,
+                        levels: within,
+                    })
+                    .map(|_| entity),
+                ),
+                args::ManagerCommand::Spend {
+                    entity,
+                    share,
+                    payback,
+                } => Some(
+                    game.add_manager_rule(ManagerRule::SpendShare {
+                        entity: entity.clone(),
+                        share: share as f64 / 100.0,
+                        max_payback_seconds: payback.as_secs(),
+                    })
+                    .map(|_| entity),
+                ),
+                args::ManagerCommand::Dismiss { entity } => {
+                    Some(game.dismiss_manager(&entity).map(|_| entity))
+                }
+            }
+            .transpose()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            output::managers(format, &game.manager_overview(), changed.as_deref())?;
+
+            config.save(game)
+        }
+        args::BasicCommand::Prestige(prestige_args) => {
+            let mut game = config.load(&config.load_catalog()?)?;
+            game.update();
+
+            let summary = if prestige_args.preview {
+                game.prestige_preview()
+            } else {
+                game.prestige()
+            };
+            output::prestige(format, &summary, prestige_args.preview)?;
+
+            config.save(game)
+        }
+        args::BasicCommand::Save(save_args) => match save_args.command {
+            args::SaveCommand::Migrate(migrate_args) => {
+                let report = config.migrate(migrate_args.dry_run)?;
+                output::migration(format, &report, migrate_args.dry_run)
+            }
+        },
+        args::BasicCommand::Restore(restore_args) => {
+            if let Some(backup_name) = restore_args.backup {
+                let backup = config.restore_backup(&backup_name)?;
+                output::restore(format, &backup)
+            } else {
+                output::backups(format, &config.list_backups()?)
+            }
+        }
+    }
+}
+
+fn find_research_info(game: &Game, node: &str) -> io::Result<ResearchNodeInformation> {
+    game.research_info(node).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown research {}, see `caru research list`", node),
+        )
+    })
+}
+
+fn get_amount_from_entity_count_arg(
+    arg: &args::EntityCountArg,
+    entity_info: &IdleEntityInformation,
+) -> io::Result<u32> {
+    match arg {
+        args::EntityCountArg::One => Ok(1),
+        args::EntityCountArg::All => Ok(u32::MAX),
+        args::EntityCountArg::Amount(n) => Ok(*n),
+        args::EntityCountArg::Milestone => entity_info
+            .next_milestone
+            .as_ref()
+            .map(|milestone| milestone.levels_remaining)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("There is no further milestone for {}", entity_info.name),
+                )
+            }),
+    }
+}
+
+fn get_strategy_from_spending_strategy_arg(arg: &args::SpendingStrategyArg) -> UpgradeStrategy {
+    match arg {
+        args::SpendingStrategyArg::Cheapest => UpgradeStrategy::Cheapest,
+        args::SpendingStrategyArg::Efficiency => UpgradeStrategy::Efficiency,
+        args::SpendingStrategyArg::RoundRobin => UpgradeStrategy::RoundRobin,
     }
 }
 
-fn get_amount_from_entity_count_arg(arg: &args::EntityCountArg) -> u32 {
+fn get_strategy_from_simulation_strategy_arg(
+    arg: &args::SimulationStrategyArg,
+) -> Option<UpgradeStrategy> {
     match arg {
-        args::EntityCountArg::One => 1,
-        args::EntityCountArg::All => u32::MAX,
-        args::EntityCountArg::Amount(n) => *n,
+        args::SimulationStrategyArg::Greedy => Some(UpgradeStrategy::Efficiency),
+        args::SimulationStrategyArg::Cheapest => Some(UpgradeStrategy::Cheapest),
+        args::SimulationStrategyArg::None => None,
     }
 }
@@ -0,0 +1,1012 @@
+// Output of the command results in the format chosen with `--output`
+//
+// JSON: every command prints one object with a `schema_version`. The version
+// is increased whenever a field is renamed, removed or changes its meaning,
+// new fields may be added at any time. Gold amounts are plain numbers, or
+// `{"mantissa": m, "exponent": e}` meaning `m * 2^(256 * e)` once they no
+// longer fit into a double. `gold_per_second_percent` is a share in [0, 1].
+// Every entity info carries `milestone_multiplier` and `next_milestone`
+// ({level, multiplier, levels_remaining, cost}, null after the last one)
+// and `throughput`, the share of its capacity in [0, 1] its resources allow,
+// `sell_refund_next` and `sell_refund_all` preview the refund of `sell`.
+//
+//   status          game_info, entity_infos, new_achievements, resources
+//                   [{name, stockpile, production_per_second,
+//                   consumption_per_second}], campaign (see campaign list),
+//                   campaign_outcome (null unless decided by this update)
+//                   {region, victory, win_chance, gold_won, losses [{entity,
+//                   levels}]}, events (rolled by this update) [{
+//                   at_unix_seconds, name, description}], active_events
+//                   [{name, seconds_remaining}], manager_purchases (by
+//                   this update) [{name, levels, gold_spent}],
+//                   offline_progress (null unless the returns diminished)
+//                   {away_seconds, credited_seconds, cap_seconds},
+//                   elapsed_seconds. game_info has offline_cap_hours,
+//                   work_streak and work_cooldown_seconds.
+//   work            too_early, gold_earned, streak, combo_multiplier,
+//                   cooldown_seconds, penalty_seconds (added by working
+//                   during the cooldown), works_left_today (paid works
+//                   left within the last day)
+//   achievements    achievements [{id, name, description, bonus,
+//                   unlocked_at_unix_seconds (null while locked), progress
+//                   in [0, 1], current, target}], achievement_multiplier
+//   upgrade <name>  upgrade {name, requested, successful} (null without a
+//                   count), info (null without --info)
+//   sell <name>     name, levels (sold), refund, level (after the sale)
+//   upgrade all     upgrades [{name, levels, gold_spent}], total_gold_spent
+//   advise          advice [{name, level, levels, cost, gold_per_second_gain,
+//                   payback_seconds, seconds_until_affordable}], plan (null
+//                   without --horizon) [{name, start_seconds, from_level,
+//                   levels, gold_spent, gold_per_second_after}]. Infinite
+//                   seconds are null.
+//   simulate        duration_seconds, gold_earned, gold_spent, timeline
+//                   [{elapsed_seconds, upgrades [{name, levels, gold_spent}],
+//                   gold_per_second_after}], final_state {game_info,
+//                   entity_infos}
+//   research list   research_points, nodes [{id, name, description, cost,
+//                   prerequisites [id], required_levels [{entity, level}],
+//                   researched, missing_requirements [text]}]
+//   research info   research_points, researched_now, node {see research
+//   research buy    list}, researched_now is only true after a purchase
+//   campaign list   army_strength, territory_multiplier, campaign (null
+//                   without one) {region, strength, win_chance,
+//                   seconds_remaining}, regions [{id, name, difficulty,
+//                   duration_seconds, gold_reward, territory_bonus,
+//                   conquered, win_chance}]
+//   campaign start  campaign {see campaign list}
+//   edict           changed (edict set or cleared, null for list), slots,
+//                   next_slot_level (null at the last slot),
+//                   cooldown_seconds, edicts [{id, name, description, slot
+//                   (null unless proclaimed), active}]
+//   manager         changed (entity whose rules changed, null for list),
+//                   managers [{entity, unlocked, unlocked_by, rules
+//                   [text]}], purchases [{at_unix_seconds, entity, levels,
+//                   gold_spent}]
+//   prestige        preview, lifetime_gold, points_before, points_gained,
+//                   multiplier_before, multiplier_after,
+//                   lifetime_gold_for_next_point
+//   init            profile, game_file_path, created_game_directory
+//   profile create  (same as init)
+//   delete          profile (null for --all), removed_path
+//   profile delete  (same as delete)
+//   profile list    profiles [{name, is_default, is_current}]
+//   profile copy    source, target
+//   profile switch  default_profile
+//   restore         backups [{name, created_unix_seconds}] for --list,
+//                   restored {name, created_unix_seconds} otherwise
+//   save migrate    dry_run, from_version, to_version, steps, changes
+//
+// CSV: a header row with the same field names, followed by one row per
+// entity, upgrade, profile, backup or migration change. `upgrade <name>`
+// prints one row with the upgrade and the entity columns of `status`, the
+// columns of the part not asked for stay empty. `advise` prints the
+// plan if a horizon is given and the advice otherwise, `simulate` one row
+// per upgraded entity and step, `achievements` one row per achievement,
+// `research` one row per node with the requirements joined by `;`,
+// `campaign` one row per region or for the started campaign, `edict` one
+// row per edict, `manager` one row per manager with the rules joined by `;`.
+
+use std::{
+    io::{self, Write},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use serde::Serialize;
+
+use crate::{
+    args::OutputFormat,
+    config::{Backup, Config},
+    game::{
+        game_state::{
+            AchievementInformation, CampaignInformation, EdictOverview, GameState,
+            IdleEntityInformation, ManagerOverview, PlannedPurchase, PrestigeSummary,
+            RegionInformation, ResearchNodeInformation, SellSummary, SimulationReport,
+            UpgradeAdvice, UpgradeResult, UpgradeSummary, WorkSummary,
+        },
+        Gold,
+    },
+    profile::ProfileInfo,
+    save::MigrationReport,
+    tui,
+};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Document<T: Serialize> {
+    schema_version: u32,
+    #[serde(flatten)]
+    content: T,
+}
+
+#[derive(Serialize)]
+struct StatusDocument<'a> {
+    #[serde(flatten)]
+    game_state: &'a GameState,
+    elapsed_seconds: f64,
+}
+
+#[derive(Serialize)]
+struct UpgradeDocument<'a> {
+    upgrade: Option<&'a UpgradeResult>,
+    info: Option<&'a IdleEntityInformation>,
+}
+
+#[derive(Serialize)]
+struct UpgradeAllDocument<'a> {
+    upgrades: &'a [UpgradeSummary],
+    total_gold_spent: Gold,
+}
+
+#[derive(Serialize)]
+struct AdviceDocument<'a> {
+    advice: &'a [UpgradeAdvice],
+    plan: Option<&'a [PlannedPurchase]>,
+}
+
+#[derive(Serialize)]
+struct AchievementsDocument<'a> {
+    achievements: &'a [AchievementInformation],
+    achievement_multiplier: f64,
+}
+
+#[derive(Serialize)]
+struct ResearchDocument<'a> {
+    research_points: f64,
+    nodes: &'a [ResearchNodeInformation],
+}
+
+#[derive(Serialize)]
+struct ResearchNodeDocument<'a> {
+    research_points: f64,
+    researched_now: bool,
+    node: &'a ResearchNodeInformation,
+}
+
+#[derive(Serialize)]
+struct RegionsDocument<'a> {
+    army_strength: f64,
+    territory_multiplier: f64,
+    campaign: Option<&'a CampaignInformation>,
+    regions: &'a [RegionInformation],
+}
+
+#[derive(Serialize)]
+struct CampaignDocument<'a> {
+    campaign: &'a CampaignInformation,
+}
+
+#[derive(Serialize)]
+struct EdictsDocument<'a> {
+    changed: Option<&'a str>,
+    #[serde(flatten)]
+    overview: &'a EdictOverview,
+}
+
+#[derive(Serialize)]
+struct ManagersDocument<'a> {
+    changed: Option<&'a str>,
+    #[serde(flatten)]
+    overview: &'a ManagerOverview,
+}
+
+#[derive(Serialize)]
+struct PrestigeDocument<'a> {
+    preview: bool,
+    #[serde(flatten)]
+    summary: &'a PrestigeSummary,
+}
+
+#[derive(Serialize)]
+struct InitDocument<'a> {
+    profile: &'a str,
+    game_file_path: String,
+    created_game_directory: bool,
+}
+
+#[derive(Serialize)]
+struct DeleteDocument<'a> {
+    profile: Option<&'a str>,
+    removed_path: String,
+}
+
+#[derive(Serialize)]
+struct ProfileCopyDocument<'a> {
+    source: &'a str,
+    target: &'a str,
+}
+
+#[derive(Serialize)]
+struct ProfileSwitchDocument<'a> {
+    default_profile: &'a str,
+}
+
+#[derive(Serialize)]
+struct ProfilesDocument<'a> {
+    profiles: Vec<ProfileEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct ProfileEntry<'a> {
+    name: &'a str,
+    is_default: bool,
+    is_current: bool,
+}
+
+#[derive(Serialize)]
+struct BackupsDocument<'a> {
+    backups: Vec<BackupEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct RestoreDocument<'a> {
+    restored: BackupEntry<'a>,
+}
+
+#[derive(Serialize)]
+struct BackupEntry<'a> {
+    name: &'a str,
+    created_unix_seconds: u64,
+}
+
+impl<'a> From<&'a Backup> for BackupEntry<'a> {
+    fn from(backup: &'a Backup) -> Self {
+        BackupEntry {
+            name: &backup.name,
+            created_unix_seconds: backup
+                .created
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct MigrationDocument<'a> {
+    dry_run: bool,
+    #[serde(flatten)]
+    report: &'a MigrationReport,
+}
+
+pub fn status(
+    format: OutputFormat,
+    game_state: &GameState,
+    delta_time: &Duration,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::display_status(game_state, delta_time),
+        OutputFormat::Json => print_json(StatusDocument {
+            game_state,
+            elapsed_seconds: delta_time.as_secs_f64(),
+        })?,
+        OutputFormat::Csv => print_csv(
+            &[&["name"], ENTITY_CSV_HEADER].concat(),
+            game_state
+                .entity_infos
+                .iter()
+                .map(|entity_info| {
+                    [vec![entity_info.name.clone()], entity_csv_row(entity_info)].concat()
+                })
+                .collect(),
+        )?,
+    }
+    Ok(())
+}
+
+/// Columns of an entity info besides its name
+const ENTITY_CSV_HEADER: &[&str] = &[
+    "level",
+    "gold_per_second",
+    "gold_per_second_percent",
+    "maximum_upgrade_quantity",
+    "upgrade_cost_next",
+    "upgrade_cost_max",
+    "throughput",
+];
+
+fn entity_csv_row(entity_info: &IdleEntityInformation) -> Vec<String> {
+    vec![
+        entity_info.level.to_string(),
+        entity_info.gold_per_second.to_string(),
+        entity_info.gold_per_second_percent.to_string(),
+        entity_info.maximum_upgrade_quantity.to_string(),
+        entity_info.upgrade_cost_next.to_string(),
+        entity_info.upgrade_cost_max.to_string(),
+        entity_info.throughput.to_string(),
+    ]
+}
+
+pub fn upgrade_entity(
+    format: OutputFormat,
+    upgrade_result: Option<&UpgradeResult>,
+    entity_info: Option<&IdleEntityInformation>,
+    game_state: &GameState,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => {
+            if let Some(upgrade_result) = upgrade_result {
+                tui::confirm_upgrade_info(
+                    upgrade_result.successful,
+                    upgrade_result.requested,
+                    &upgrade_result.name,
+                );
+            }
+            if let Some(entity_info) = entity_info {
+                tui::display_upgrade_info(entity_info, game_state);
+            }
+        }
+        OutputFormat::Json => print_json(UpgradeDocument {
+            upgrade: upgrade_result,
+            info: entity_info,
+        })?,
+        OutputFormat::Csv => {
+            let name = upgrade_result
+                .map(|upgrade_result| upgrade_result.name.clone())
+                .or_else(|| entity_info.map(|entity_info| entity_info.name.clone()));
+            let upgrade_columns = upgrade_result.map_or(vec![String::new(); 2], |upgrade_result| {
+                vec![
+                    upgrade_result.requested.to_string(),
+                    upgrade_result.successful.to_string(),
+                ]
+            });
+            let entity_columns =
+                entity_info.map_or(vec![String::new(); ENTITY_CSV_HEADER.len()], entity_csv_row);
+            print_csv(
+                &[&["name", "requested", "successful"], ENTITY_CSV_HEADER].concat(),
+                name.map(|name| [vec![name], upgrade_columns, entity_columns].concat())
+                    .into_iter()
+                    .collect(),
+            )?
+        }
+    }
+    Ok(())
+}
+
+pub fn upgrade_all(format: OutputFormat, summaries: &[UpgradeSummary]) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::display_upgrade_all_summary(summaries),
+        OutputFormat::Json => print_json(UpgradeAllDocument {
+            upgrades: summaries,
+            total_gold_spent: summaries.iter().map(|summary| summary.gold_spent).sum(),
+        })?,
+        OutputFormat::Csv => print_csv(
+            &["name", "levels", "gold_spent"],
+            summaries
+                .iter()
+                .map(|summary| {
+                    vec![
+                        summary.name.clone(),
+                        summary.levels.to_string(),
+                        summary.gold_spent.to_string(),
+                    ]
+                })
+                .collect(),
+        )?,
+    }
+    Ok(())
+}
+
+pub fn advice(
+    format: OutputFormat,
+    advice: &[UpgradeAdvice],
+    plan: Option<(&[PlannedPurchase], &Duration)>,
+) -> io::Result<()> {
+    match (format, plan) {
+        (OutputFormat::Text, _) => {
+            tui::display_advice(advice);
+            if let Some((plan, horizon)) = plan {
+                tui::display_purchase_plan(plan, horizon);
+            }
+        }
+        (OutputFormat::Json, _) => print_json(AdviceDocument {
+            advice,
+            plan: plan.map(|(plan, _)| plan),
+        })?,
+        (OutputFormat::Csv, Some((plan, _))) => print_csv(
+            &[
+                "name",
+                "start_seconds",
+                "from_level",
+                "levels",
+                "gold_spent",
+                "gold_per_second_after",
+            ],
+            plan.iter()
+                .map(|purchase| {
+                    vec![
+                        purchase.name.clone(),
+                        purchase.start_seconds.to_string(),
+                        purchase.from_level.to_string(),
+                        purchase.levels.to_string(),
+                        purchase.gold_spent.to_string(),
+                        purchase.gold_per_second_after.to_string(),
+                    ]
+                })
+                .collect(),
+        )?,
+        (OutputFormat::Csv, None) => print_csv(
+            &[
+                "name",
+                "level",
+                "levels",
+                "cost",
+                "gold_per_second_gain",
+                "payback_seconds",
+                "seconds_until_affordable",
+            ],
+            advice
+                .iter()
+                .map(|entry| {
+                    vec![
+                        entry.name.clone(),
+                        entry.level.to_string(),
+                        entry.levels.to_string(),
+                        entry.cost.to_string(),
+                        entry.gold_per_second_gain.to_string(),
+                        entry.payback_seconds.to_string(),
+                        entry.seconds_until_affordable.to_string(),
+                    ]
+                })
+                .collect(),
+        )?,
+    }
+    Ok(())
+}
+
+pub fn simulation(format: OutputFormat, report: &SimulationReport) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::display_simulation(report),
+        OutputFormat::Json => print_json(report)?,
+        OutputFormat::Csv => print_csv(
+            &["elapsed_seconds", "name", "levels", "gold_spent"],
+            report
+                .timeline
+                .iter()
+                .flat_map(|step| {
+                    step.upgrades.iter().map(|summary| {
+                        vec![
+                            step.elapsed_seconds.to_string(),
+                            summary.name.clone(),
+                            summary.levels.to_string(),
+                            summary.gold_spent.to_string(),
+                        ]
+                    })
+                })
+                .collect(),
+        )?,
+    }
+    Ok(())
+}
+
+pub fn achievements(
+    format: OutputFormat,
+    achievements: &[AchievementInformation],
+    game_state: &GameState,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::display_achievements(achievements, game_state),
+        OutputFormat::Json => print_json(AchievementsDocument {
+            achievements,
+            achievement_multiplier: game_state.game_info.achievement_multiplier,
+        })?,
+        OutputFormat::Csv => print_csv(
+            &[
+                "id",
+                "name",
+                "bonus",
+                "unlocked_at_unix_seconds",
+                "progress",
+                "current",
+                "target",
+            ],
+            achievements
+                .iter()
+                .map(|achievement| {
+                    vec![
+                        achievement.id.clone(),
+                        achievement.name.clone(),
+                        achievement.bonus.to_string(),
+                        achievement
+                            .unlocked_at_unix_seconds
+                            .map(|seconds| seconds.to_string())
+                            .unwrap_or_default(),
+                        achievement.progress.to_string(),
+                        achievement.current.to_string(),
+                        achievement.target.to_string(),
+                    ]
+                })
+                .collect(),
+        )?,
+    }
+    Ok(())
+}
+
+pub fn research(
+    format: OutputFormat,
+    nodes: &[ResearchNodeInformation],
+    research_points: f64,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::display_research(nodes, research_points),
+        OutputFormat::Json => print_json(ResearchDocument {
+            research_points,
+            nodes,
+        })?,
+        OutputFormat::Csv => print_research_csv(nodes)?,
+    }
+    Ok(())
+}
+
+pub fn research_node(
+    format: OutputFormat,
+    node: &ResearchNodeInformation,
+    research_points: f64,
+    researched_now: bool,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => {
+            if researched_now {
+                tui::confirm_research(node);
+            }
+            tui::display_research_node(node, research_points)
+        }
+        OutputFormat::Json => print_json(ResearchNodeDocument {
+            research_points,
+            researched_now,
+            node,
+        })?,
+        OutputFormat::Csv => print_research_csv(std::slice::from_ref(node))?,
+    }
+    Ok(())
+}
+
+fn print_research_csv(nodes: &[ResearchNodeInformation]) -> io::Result<()> {
+    print_csv(
+        &[
+            "id",
+            "name",
+            "cost",
+            "researched",
+            "prerequisites",
+            "missing_requirements",
+        ],
+        nodes
+            .iter()
+            .map(|node| {
+                vec![
+                    node.id.clone(),
+                    node.name.clone(),
+                    node.cost.to_string(),
+                    node.researched.to_string(),
+                    node.prerequisites.join(";"),
+                    node.missing_requirements.join(";"),
+                ]
+            })
+            .collect(),
+    )
+}
+
+pub fn regions(
+    format: OutputFormat,
+    regions: &[RegionInformation],
+    game_state: &GameState,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::display_regions(regions, game_state),
+        OutputFormat::Json => print_json(RegionsDocument {
+            army_strength: game_state.game_info.army_strength,
+            territory_multiplier: game_state.game_info.territory_multiplier,
+            campaign: game_state.campaign.as_ref(),
+            regions,
+        })?,
+        OutputFormat::Csv => print_csv(
+            &[
+                "id",
+                "name",
+                "difficulty",
+                "duration_seconds",
+                "gold_reward",
+                "territory_bonus",
+                "conquered",
+                "win_chance",
+            ],
+            regions
+                .iter()
+                .map(|region| {
+                    vec![
+                        region.id.clone(),
+                        region.name.clone(),
+                        region.difficulty.to_string(),
+                        region.duration_seconds.to_string(),
+                        region.gold_reward.to_string(),
+                        region.territory_bonus.to_string(),
+                        region.conquered.to_string(),
+                        region.win_chance.to_string(),
+                    ]
+                })
+                .collect(),
+        )?,
+    }
+    Ok(())
+}
+
+pub fn campaign(format: OutputFormat, campaign: &CampaignInformation) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::confirm_campaign(campaign),
+        OutputFormat::Json => print_json(CampaignDocument { campaign })?,
+        OutputFormat::Csv => print_csv(
+            &["region", "strength", "win_chance", "seconds_remaining"],
+            vec![vec![
+                campaign.region.clone(),
+                campaign.strength.to_string(),
+                campaign.win_chance.to_string(),
+                campaign.seconds_remaining.to_string(),
+            ]],
+        )?,
+    }
+    Ok(())
+}
+
+pub fn edicts(
+    format: OutputFormat,
+    overview: &EdictOverview,
+    changed: Option<&str>,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::display_edicts(overview, changed),
+        OutputFormat::Json => print_json(EdictsDocument { changed, overview })?,
+        OutputFormat::Csv => print_csv(
+            &["id", "name", "description", "slot", "active"],
+            overview
+                .edicts
+                .iter()
+                .map(|edict| {
+                    vec![
+                        edict.id.clone(),
+                        edict.name.clone(),
+                        edict.description.clone(),
+                        edict.slot.map(|slot| slot.to_string()).unwrap_or_default(),
+                        edict.active.to_string(),
+                    ]
+                })
+                .collect(),
+        )?,
+    }
+    Ok(())
+}
+
+pub fn managers(
+    format: OutputFormat,
+    overview: &ManagerOverview,
+    changed: Option<&str>,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::display_managers(overview, changed),
+        OutputFormat::Json => print_json(ManagersDocument { changed, overview })?,
+        OutputFormat::Csv => print_csv(
+            &["entity", "unlocked", "unlocked_by", "rules"],
+            overview
+                .managers
+                .iter()
+                .map(|manager| {
+                    vec![
+                        manager.entity.clone(),
+                        manager.unlocked.to_string(),
+                        manager.unlocked_by.clone(),
+                        manager.rules.join(";"),
+                    ]
+                })
+                .collect(),
+        )?,
+    }
+    Ok(())
+}
+
+pub fn sell(format: OutputFormat, summary: &SellSummary) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::confirm_sell(summary),
+        OutputFormat::Json => print_json(summary)?,
+        OutputFormat::Csv => print_csv(
+            &["name", "levels", "refund", "level"],
+            vec![vec![
+                summary.name.clone(),
+                summary.levels.to_string(),
+                summary.refund.to_string(),
+                summary.level.to_string(),
+            ]],
+        )?,
+    }
+    Ok(())
+}
+
+pub fn work(format: OutputFormat, summary: &WorkSummary) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::display_work(summary),
+        OutputFormat::Json => print_json(summary)?,
+        OutputFormat::Csv => print_csv(
+            &[
+                "too_early",
+                "gold_earned",
+                "streak",
+                "combo_multiplier",
+                "cooldown_seconds",
+                "penalty_seconds",
+                "works_left_today",
+            ],
+            vec![vec![
+                summary.too_early.to_string(),
+                summary.gold_earned.to_string(),
+                summary.streak.to_string(),
+                summary.combo_multiplier.to_string(),
+                summary.cooldown_seconds.to_string(),
+                summary.penalty_seconds.to_string(),
+                summary.works_left_today.to_string(),
+            ]],
+        )?,
+    }
+    Ok(())
+}
+
+pub fn prestige(format: OutputFormat, summary: &PrestigeSummary, preview: bool) -> io::Result<()> {
+    match format {
+        OutputFormat::Text if preview => tui::display_prestige_preview(summary),
+        OutputFormat::Text => tui::confirm_prestige(summary),
+        OutputFormat::Json => print_json(PrestigeDocument { preview, summary })?,
+        OutputFormat::Csv => print_csv(
+            &[
+                "preview",
+                "lifetime_gold",
+                "points_before",
+                "points_gained",
+                "multiplier_before",
+                "multiplier_after",
+                "lifetime_gold_for_next_point",
+            ],
+            vec![vec![
+                preview.to_string(),
+                summary.lifetime_gold.to_string(),
+                summary.points_before.to_string(),
+                summary.points_gained.to_string(),
+                summary.multiplier_before.to_string(),
+                summary.multiplier_after.to_string(),
+                summary.lifetime_gold_for_next_point.to_string(),
+            ]],
+        )?,
+    }
+    Ok(())
+}
+
+pub fn init(format: OutputFormat, config: &Config, created_game_directory: bool) -> io::Result<()> {
+    let document = InitDocument {
+        profile: &config.profile,
+        game_file_path: config.game_file_path.display().to_string(),
+        created_game_directory,
+    };
+
+    match format {
+        OutputFormat::Text => tui::confirm_init(config, created_game_directory),
+        OutputFormat::Json => print_json(document)?,
+        OutputFormat::Csv => print_csv(
+            &["profile", "game_file_path", "created_game_directory"],
+            vec![vec![
+                document.profile.to_string(),
+                document.game_file_path,
+                created_game_directory.to_string(),
+            ]],
+        )?,
+    }
+    Ok(())
+}
+
+/// Confirms the removal of the profile of `config` or of the whole game
+/// directory for `all`
+pub fn delete(format: OutputFormat, config: &Config, all: bool) -> io::Result<()> {
+    let removed_path: &Path = if all {
+        &config.game_directory
+    } else {
+        &config.game_file_path
+    };
+    let document = DeleteDocument {
+        profile: (!all).then_some(config.profile.as_str()),
+        removed_path: removed_path.display().to_string(),
+    };
+
+    match format {
+        OutputFormat::Text => tui::confirm_delete(config, all),
+        OutputFormat::Json => print_json(document)?,
+        OutputFormat::Csv => print_csv(
+            &["profile", "removed_path"],
+            vec![vec![
+                document.profile.unwrap_or_default().to_string(),
+                document.removed_path,
+            ]],
+        )?,
+    }
+    Ok(())
+}
+
+pub fn profile_copy(format: OutputFormat, source: &str, target: &str) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::confirm_profile_copy(source, target),
+        OutputFormat::Json => print_json(ProfileCopyDocument { source, target })?,
+        OutputFormat::Csv => print_csv(
+            &["source", "target"],
+            vec![vec![source.to_string(), target.to_string()]],
+        )?,
+    }
+    Ok(())
+}
+
+pub fn profile_switch(format: OutputFormat, profile: &str) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::confirm_profile_switch(profile),
+        OutputFormat::Json => print_json(ProfileSwitchDocument {
+            default_profile: profile,
+        })?,
+        OutputFormat::Csv => print_csv(&["default_profile"], vec![vec![profile.to_string()]])?,
+    }
+    Ok(())
+}
+
+pub fn profiles(
+    format: OutputFormat,
+    profiles: &[ProfileInfo],
+    current_profile: &str,
+) -> io::Result<()> {
+    let entries: Vec<ProfileEntry> = profiles
+        .iter()
+        .map(|profile| ProfileEntry {
+            name: &profile.name,
+            is_default: profile.is_default,
+            is_current: profile.name == current_profile,
+        })
+        .collect();
+
+    match format {
+        OutputFormat::Text => tui::display_profiles(profiles, current_profile),
+        OutputFormat::Json => print_json(ProfilesDocument { profiles: entries })?,
+        OutputFormat::Csv => print_csv(
+            &["name", "is_default", "is_current"],
+            entries
+                .iter()
+                .map(|entry| {
+                    vec![
+                        entry.name.to_string(),
+                        entry.is_default.to_string(),
+                        entry.is_current.to_string(),
+                    ]
+                })
+                .collect(),
+        )?,
+    }
+    Ok(())
+}
+
+pub fn backups(format: OutputFormat, backups: &[Backup]) -> io::Result<()> {
+    let entries: Vec<BackupEntry> = backups.iter().map(BackupEntry::from).collect();
+
+    match format {
+        OutputFormat::Text => tui::display_backups(backups),
+        OutputFormat::Json => print_json(BackupsDocument { backups: entries })?,
+        OutputFormat::Csv => print_backup_csv(&entries)?,
+    }
+    Ok(())
+}
+
+pub fn restore(format: OutputFormat, backup: &Backup) -> io::Result<()> {
+    let entry = BackupEntry::from(backup);
+
+    match format {
+        OutputFormat::Text => tui::confirm_restore(backup),
+        OutputFormat::Json => print_json(RestoreDocument { restored: entry })?,
+        OutputFormat::Csv => print_backup_csv(&[entry])?,
+    }
+    Ok(())
+}
+
+fn print_backup_csv(entries: &[BackupEntry]) -> io::Result<()> {
+    print_csv(
+        &["name", "created_unix_seconds"],
+        entries
+            .iter()
+            .map(|entry| {
+                vec![
+                    entry.name.to_string(),
+                    entry.created_unix_seconds.to_string(),
+                ]
+            })
+            .collect(),
+    )
+}
+
+pub fn migration(format: OutputFormat, report: &MigrationReport, dry_run: bool) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => tui::display_migration_report(report, dry_run),
+        OutputFormat::Json => print_json(MigrationDocument { dry_run, report })?,
+        OutputFormat::Csv => print_csv(
+            &["change"],
+            report
+                .changes
+                .iter()
+                .map(|change| vec![change.clone()])
+                .collect(),
+        )?,
+    }
+    Ok(())
+}
+
+// Both write through a locked stdout instead of `println!`, so a closed
+// pipe is returned as error instead of panicking
+fn print_json<T: Serialize>(content: T) -> io::Result<()> {
+    let document = Document {
+        schema_version: SCHEMA_VERSION,
+        content,
+    };
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", serde_json::to_string_pretty(&document)?)
+}
+
+fn print_csv(header: &[&str], rows: Vec<Vec<String>>) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", header.join(","))?;
+    for row in rows {
+        let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        writeln!(stdout, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+/// Quotes a field if it contains a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{csv_field, Document, StatusDocument, SCHEMA_VERSION};
+    use crate::game::{catalog::Catalog, Game};
+
+    #[test]
+    fn status_document_has_stable_fields() {
+        let game_state = Game::new(&Catalog::builtin()).create_game_state();
+        let document = serde_json::to_value(Document {
+            schema_version: SCHEMA_VERSION,
+            content: StatusDocument {
+                game_state: &game_state,
+                elapsed_seconds: 1.5,
+            },
+        })
+        .unwrap();
+
+        assert_eq!(json!(SCHEMA_VERSION), document["schema_version"]);
+        assert_eq!(json!(1.5), document["elapsed_seconds"]);
+        for field in [
+            "gold",
+            "gold_per_second",
+            "lifetime_gold",
+            "prestige_points",
+        ] {
+            assert!(document["game_info"].get(field).is_some(), "{}", field);
+        }
+        let entity_info = &document["entity_infos"][0];
+        assert_eq!(json!("Lumberjack"), entity_info["name"]);
+        assert_eq!(json!(0), entity_info["level"]);
+        assert!(entity_info["upgrade_cost_next"].is_number());
+        assert_eq!(json!(25), entity_info["next_milestone"]["level"]);
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!("Lumberjack", csv_field("Lumberjack"));
+        assert_eq!("\"a,b\"", csv_field("a,b"));
+        assert_eq!("\"say \"\"hi\"\"\"", csv_field("say \"hi\""));
+    }
+}
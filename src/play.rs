@@ -0,0 +1,211 @@
+// Interactive full screen mode
+
+use std::{
+    io::{self, Stdout, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
+    terminal::{self, Clear, ClearType},
+};
+
+use crate::{
+    config::Config,
+    game::{
+        catalog::Catalog,
+        game_state::{GameState, IdleEntityInformation},
+        Game,
+    },
+};
+
+/// Time between two frames, gold ticks up at this rate
+const FRAME_DURATION: Duration = Duration::from_millis(100);
+/// Time between two automatic saves
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Puts the terminal into full screen mode and restores it when dropped,
+/// also when the session ends with an error
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter(stdout: &mut Stdout) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the interactive session until the player quits.
+///
+/// The game directory is only locked while the game is saved, so other caru
+/// invocations keep working while playing. Every purchase is applied to the
+/// freshly loaded save to not overwrite their progress.
+pub fn play(config: &Config, lock_timeout: Duration) -> io::Result<()> {
+    let catalog = config.load_catalog()?;
+    let (mut game, _) = sync_game(config, &catalog, lock_timeout, |_| ())?;
+
+    let mut stdout = io::stdout();
+    let _terminal_guard = TerminalGuard::enter(&mut stdout)?;
+
+    let mut selected = 0;
+    let mut message = String::new();
+    let mut last_save = Instant::now();
+    loop {
+        let game_state = game.update();
+        render(&mut stdout, config, &game_state, selected, &message)?;
+
+        if event::poll(FRAME_DURATION)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+
+                let amount = match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        selected = selected.saturating_sub(1);
+                        None
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        selected = (selected + 1).min(game_state.entity_infos.len() - 1);
+                        None
+                    }
+                    KeyCode::Char('1') => Some(1),
+                    KeyCode::Char('t') => Some(10),
+                    KeyCode::Char('m') => Some(u32::MAX),
+                    _ => None,
+                };
+
+                if let Some(amount) = amount {
+                    let entity_name = game_state.entity_infos[selected].name.clone();
+                    let (saved_game, successful_upgrades) =
+                        sync_game(config, &catalog, lock_timeout, |game| {
+                            game.upgrade(&entity_name, amount)
+                        })?;
+                    game = saved_game;
+                    last_save = Instant::now();
+                    message = purchase_message(successful_upgrades, amount, &entity_name);
+                }
+            }
+        }
+
+        if last_save.elapsed() >= SAVE_INTERVAL {
+            (game, _) = sync_game(config, &catalog, lock_timeout, |_| ())?;
+            last_save = Instant::now();
+        }
+    }
+
+    sync_game(config, &catalog, lock_timeout, |_| ())?;
+    Ok(())
+}
+
+/// Applies `action` to the save within a locked load-update-save cycle and
+/// returns the saved game together with the result of the action
+fn sync_game<T>(
+    config: &Config,
+    catalog: &Catalog,
+    lock_timeout: Duration,
+    action: impl FnOnce(&mut Game) -> T,
+) -> io::Result<(Game, T)> {
+    let _game_lock = config.lock(lock_timeout)?;
+    let mut game = config.load(catalog)?;
+    game.update();
+
+    let result = action(&mut game);
+    config.save(game.clone())?;
+    Ok((game, result))
+}
+
+fn purchase_message(successful_upgrades: u32, amount: u32, entity_name: &str) -> String {
+    if amount == u32::MAX {
+        format!("Upgraded {} {} times", entity_name, successful_upgrades)
+    } else {
+        format!(
+            "Upgraded {}/{} {}",
+            successful_upgrades, amount, entity_name
+        )
+    }
+}
+
+fn render(
+    stdout: &mut Stdout,
+    config: &Config,
+    game_state: &GameState,
+    selected: usize,
+    message: &str,
+) -> io::Result<()> {
+    queue!(stdout, cursor::MoveTo(0, 0))?;
+
+    print_line(stdout, &format!("caru - profile {}", config.profile))?;
+    print_line(
+        stdout,
+        &format!(
+            "Gold: {:.2} [{:.2} g/s]",
+            game_state.game_info.gold, game_state.game_info.gold_per_second
+        ),
+    )?;
+    print_line(stdout, "")?;
+    print_line(
+        stdout,
+        &format!(
+            "  {:<16} {:>6} {:>12} {:>7} {:>16} {:>6}",
+            "Entity", "Level", "g/s", "Share", "Next upgrade", "Max"
+        ),
+    )?;
+
+    for (idx, entity_info) in game_state.entity_infos.iter().enumerate() {
+        if idx == selected {
+            queue!(stdout, SetAttribute(Attribute::Reverse))?;
+        }
+        if entity_info.upgrade_cost_next > game_state.game_info.gold {
+            queue!(stdout, SetForegroundColor(Color::DarkGrey))?;
+        }
+        print_line(stdout, &entity_row(entity_info, idx == selected))?;
+        queue!(stdout, ResetColor, SetAttribute(Attribute::Reset))?;
+    }
+
+    print_line(stdout, "")?;
+    print_line(
+        stdout,
+        "[up/down] select  [1] buy 1  [t] buy 10  [m] buy max  [q] quit",
+    )?;
+    print_line(stdout, message)?;
+    queue!(stdout, Clear(ClearType::FromCursorDown))?;
+
+    stdout.flush()
+}
+
+fn entity_row(entity_info: &IdleEntityInformation, is_selected: bool) -> String {
+    format!(
+        "{} {:<16} {:>6} {:>12.2} {:>6.2}% {:>16.2} {:>6}",
+        if is_selected { ">" } else { " " },
+        entity_info.name,
+        entity_info.level,
+        entity_info.gold_per_second,
+        entity_info.gold_per_second_percent * 100.0,
+        entity_info.upgrade_cost_next,
+        entity_info.maximum_upgrade_quantity
+    )
+}
+
+/// Prints a line in raw mode, where a new line does not return the cursor
+fn print_line(stdout: &mut Stdout, line: &str) -> io::Result<()> {
+    queue!(
+        stdout,
+        Print(line),
+        Clear(ClearType::UntilNewLine),
+        Print("\r\n")
+    )
+}
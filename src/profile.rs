@@ -0,0 +1,75 @@
+// Management of the profiles in the game directory
+
+use std::{fs, io};
+
+use crate::config::{self, Config, PROFILE_FILE_EXTENSION};
+
+pub struct ProfileInfo {
+    pub name: String,
+    /// Used when no profile is given
+    pub is_default: bool,
+}
+
+/// All profiles with a save, sorted by name
+pub fn list_profiles(config: &Config) -> io::Result<Vec<ProfileInfo>> {
+    let default_profile = config.default_profile()?;
+
+    if !config.profiles_directory.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut profiles = Vec::new();
+    for dir_entry in fs::read_dir(&config.profiles_directory)? {
+        let file_name = dir_entry?.file_name();
+        let Some(name) = file_name
+            .to_str()
+            .and_then(|name| name.strip_suffix(PROFILE_FILE_EXTENSION))
+        else {
+            continue;
+        };
+        if config::validate_profile_name(name).is_err() {
+            continue;
+        }
+
+        profiles.push(ProfileInfo {
+            name: name.to_string(),
+            is_default: name == default_profile,
+        });
+    }
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(profiles)
+}
+
+/// Copies the save of the `source` profile into a new profile `target`
+pub fn copy_profile(config: &Config, source: &str, target: &str) -> io::Result<()> {
+    let source_config = config.with_profile(source)?;
+    let target_config = config.with_profile(target)?;
+    if !source_config.profile_exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("There is no profile named {}", source),
+        ));
+    }
+    if target_config.profile_exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("The profile {} already exists", target),
+        ));
+    }
+
+    fs::copy(&source_config.game_file_path, &target_config.game_file_path)?;
+    Ok(())
+}
+
+/// Makes `profile` the profile used when no profile is given
+pub fn switch_profile(config: &Config, profile: &str) -> io::Result<()> {
+    let profile_config = config.with_profile(profile)?;
+    if !profile_config.profile_exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("There is no profile named {}", profile),
+        ));
+    }
+
+    fs::write(&config.default_profile_file_path, profile)
+}
@@ -0,0 +1,381 @@
+// Save file format and migrations between its versions
+
+use std::{io, time::SystemTime};
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::game::{event::EventState, Game};
+
+const VERSION_KEY: &str = "version";
+
+/// Version written by this build. Saves without a version field are version 0.
+pub const CURRENT_VERSION: u64 = 11;
+
+/// Upgrades a save document from `from_version` to `from_version + 1`
+struct Migration {
+    from_version: u64,
+    description: &'static str,
+    migrate: fn(&mut Map<String, Value>),
+}
+
+/// All migrations, ordered by `from_version` without gaps
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from_version: 0,
+        description: "Adds lifetime gold and prestige points",
+        migrate: add_prestige,
+    },
+    Migration {
+        from_version: 1,
+        description: "Adds unlocked achievements",
+        migrate: add_achievements,
+    },
+    Migration {
+        from_version: 2,
+        description: "Adds level milestones to the entities",
+        migrate: add_milestones,
+    },
+    Migration {
+        from_version: 3,
+        description: "Adds resource stockpiles and production chains",
+        migrate: add_resources,
+    },
+    Migration {
+        from_version: 4,
+        description: "Adds the researched nodes of the tech tree",
+        migrate: add_research,
+    },
+    Migration {
+        from_version: 5,
+        description: "Adds campaigns and conquered regions",
+        migrate: add_campaigns,
+    },
+    Migration {
+        from_version: 6,
+        description: "Adds the edicts of the King",
+        migrate: add_edicts,
+    },
+    Migration {
+        from_version: 7,
+        description: "Adds random events",
+        migrate: add_events,
+    },
+    Migration {
+        from_version: 8,
+        description: "Adds the rules and purchases of the managers",
+        migrate: add_managers,
+    },
+    Migration {
+        from_version: 9,
+        description: "Adds the cooldown and combo streak of the work command",
+        migrate: add_work,
+    },
+    Migration {
+        from_version: 10,
+        description: "Adds the recent works for the daily limit of the work command",
+        migrate: add_recent_works,
+    },
+];
+
+fn add_prestige(document: &mut Map<String, Value>) {
+    // The gold at hand is the best known estimate of the gold earned so far
+    let current_gold = document
+        .get("current_gold")
+        .cloned()
+        .unwrap_or(Value::from(0.0));
+    document.entry("lifetime_gold").or_insert(current_gold);
+    document.entry("prestige_points").or_insert(Value::from(0));
+}
+
+fn add_achievements(document: &mut Map<String, Value>) {
+    // Reached goals are unlocked at the next update
+    document
+        .entry("achievements")
+        .or_insert(Value::Array(Vec::new()));
+}
+
+fn add_milestones(document: &mut Map<String, Value>) {
+    // The milestones of the catalog are applied when the game is loaded
+    if let Some(Value::Array(entities)) = document.get_mut("idle_entities") {
+        for entity in entities.iter_mut().filter_map(Value::as_object_mut) {
+            entity
+                .entry("milestones")
+                .or_insert(Value::Array(Vec::new()));
+        }
+    }
+}
+
+fn add_resources(document: &mut Map<String, Value>) {
+    // The produced and consumed resources of the catalog are applied when
+    // the game is loaded
+    if let Some(Value::Array(entities)) = document.get_mut("idle_entities") {
+        for entity in entities.iter_mut().filter_map(Value::as_object_mut) {
+            entity.entry("produces").or_insert(Value::Null);
+            entity.entry("consumes").or_insert(Value::Array(Vec::new()));
+        }
+    }
+    document
+        .entry("resources")
+        .or_insert(Value::Object(Map::new()));
+}
+
+fn add_research(document: &mut Map<String, Value>) {
+    document
+        .entry("research")
+        .or_insert(Value::Array(Vec::new()));
+}
+
+fn add_campaigns(document: &mut Map<String, Value>) {
+    document.entry("campaign").or_insert(Value::Null);
+    document
+        .entry("conquered_regions")
+        .or_insert(Value::Array(Vec::new()));
+}
+
+fn add_edicts(document: &mut Map<String, Value>) {
+    document.entry("edicts").or_insert(Value::Array(Vec::new()));
+    document.entry("edicts_changed_at").or_insert(Value::Null);
+}
+
+fn add_events(document: &mut Map<String, Value>) {
+    // The first event is rolled for the time since the last update
+    let last_time_stamp = document
+        .get("last_time_stamp")
+        .and_then(|time| serde_json::from_value(time.clone()).ok())
+        .unwrap_or_else(SystemTime::now);
+    document.entry("events").or_insert_with(|| {
+        serde_json::to_value(EventState::new(last_time_stamp)).unwrap_or(Value::Null)
+    });
+}
+
+fn add_managers(document: &mut Map<String, Value>) {
+    document
+        .entry("managers")
+        .or_insert(Value::Array(Vec::new()));
+    document
+        .entry("manager_log")
+        .or_insert(Value::Array(Vec::new()));
+}
+
+fn add_work(document: &mut Map<String, Value>) {
+    document
+        .entry("work")
+        .or_insert(json!({"available_at": null, "streak": 0}));
+}
+
+fn add_recent_works(document: &mut Map<String, Value>) {
+    // Works before the limit existed do not count towards it
+    if let Some(Value::Object(work)) = document.get_mut("work") {
+        work.entry("recent_works")
+            .or_insert(Value::Array(Vec::new()));
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MigrationReport {
+    pub from_version: u64,
+    pub to_version: u64,
+    /// Descriptions of the applied migrations
+    pub steps: Vec<String>,
+    /// Every value that was added, removed or changed
+    pub changes: Vec<String>,
+}
+
+impl MigrationReport {
+    pub fn is_up_to_date(&self) -> bool {
+        self.from_version == self.to_version
+    }
+}
+
+/// Serializes a game into a save document of the current version
+pub fn to_document(game: &Game) -> io::Result<Value> {
+    let mut document = serde_json::to_value(game)?;
+    if let Value::Object(map) = &mut document {
+        map.insert(VERSION_KEY.to_string(), Value::from(CURRENT_VERSION));
+    }
+    Ok(document)
+}
+
+/// Migrates a save document of any known version and deserializes the game
+pub fn from_document(mut document: Value) -> io::Result<Game> {
+    migrate(&mut document)?;
+    if let Value::Object(map) = &mut document {
+        map.remove(VERSION_KEY);
+    }
+    Ok(serde_json::from_value(document)?)
+}
+
+/// Upgrades a save document step by step to the current version
+pub fn migrate(document: &mut Value) -> io::Result<MigrationReport> {
+    let original = document.clone();
+    let map = document.as_object_mut().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The save file does not contain a JSON object",
+        )
+    })?;
+
+    let from_version = match map.get(VERSION_KEY) {
+        None => 0,
+        Some(version) => version.as_u64().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid save version {}", version),
+            )
+        })?,
+    };
+    if from_version > CURRENT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "The save has version {}, but this caru only supports up to version {}",
+                from_version, CURRENT_VERSION
+            ),
+        ));
+    }
+
+    let mut steps = Vec::new();
+    for migration in &MIGRATIONS[from_version as usize..] {
+        (migration.migrate)(map);
+        steps.push(format!(
+            "v{} -> v{}: {}",
+            migration.from_version,
+            migration.from_version + 1,
+            migration.description
+        ));
+    }
+    map.insert(VERSION_KEY.to_string(), Value::from(CURRENT_VERSION));
+
+    let mut changes = Vec::new();
+    if from_version != CURRENT_VERSION {
+        diff("", &original, document, &mut changes);
+    }
+
+    Ok(MigrationReport {
+        from_version,
+        to_version: CURRENT_VERSION,
+        steps,
+        changes,
+    })
+}
+
+/// Collects the differences between two documents as readable lines
+fn diff(path: &str, before: &Value, after: &Value, changes: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(before_map), Value::Object(after_map)) => {
+            for (key, before_value) in before_map {
+                let child_path = join_path(path, key);
+                match after_map.get(key) {
+                    Some(after_value) => diff(&child_path, before_value, after_value, changes),
+                    None => changes.push(format!("removed {}", child_path)),
+                }
+            }
+            for (key, after_value) in after_map {
+                if !before_map.contains_key(key) {
+                    changes.push(format!("added {} = {}", join_path(path, key), after_value));
+                }
+            }
+        }
+        (Value::Array(before_items), Value::Array(after_items))
+            if before_items.len() == after_items.len() =>
+        {
+            for (idx, (before_item, after_item)) in before_items.iter().zip(after_items).enumerate()
+            {
+                diff(
+                    &join_path(path, &idx.to_string()),
+                    before_item,
+                    after_item,
+                    changes,
+                );
+            }
+        }
+        _ if before != after => {
+            changes.push(format!("changed {}: {} -> {}", path, before, after));
+        }
+        _ => {}
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{from_document, migrate, to_document, CURRENT_VERSION};
+    use crate::game::{catalog::Catalog, Game};
+
+    fn unversioned_document() -> Value {
+        json!({
+            "init_time_stamp": {"secs_since_epoch": 1_669_000_000, "nanos_since_epoch": 0},
+            "last_time_stamp": {"secs_since_epoch": 1_669_000_100, "nanos_since_epoch": 0},
+            "current_delta_time": {"secs": 100, "nanos": 0},
+            "current_gold": 42.5,
+            "idle_entities": [{
+                "name": "Lumberjack",
+                "level": 3,
+                "base_gold_per_second": 0.1,
+                "base_upgrade_cost": 1.0,
+                "upgrade_cost_factor": 1.15
+            }]
+        })
+    }
+
+    #[test]
+    fn unversioned_save_is_migrated_to_current_version() {
+        let mut document = unversioned_document();
+
+        let report = migrate(&mut document).unwrap();
+
+        assert_eq!(0, report.from_version);
+        assert_eq!(CURRENT_VERSION, report.to_version);
+        assert_eq!(CURRENT_VERSION as usize, report.steps.len());
+        assert!(report
+            .changes
+            .iter()
+            .any(|change| change.starts_with("added prestige_points")));
+        assert_eq!(json!(CURRENT_VERSION), document["version"]);
+    }
+
+    #[test]
+    fn unversioned_save_keeps_its_prestige() {
+        let mut document = unversioned_document();
+        document["lifetime_gold"] = json!(1e6);
+        document["prestige_points"] = json!(7);
+
+        migrate(&mut document).unwrap();
+
+        assert_eq!(json!(1e6), document["lifetime_gold"]);
+        assert_eq!(json!(7), document["prestige_points"]);
+    }
+
+    #[test]
+    fn unversioned_save_loads_as_game() {
+        assert!(from_document(unversioned_document()).is_ok());
+    }
+
+    #[test]
+    fn current_save_is_up_to_date() {
+        let mut document = to_document(&Game::new(&Catalog::builtin())).unwrap();
+
+        let report = migrate(&mut document).unwrap();
+
+        assert!(report.is_up_to_date());
+        assert!(report.changes.is_empty());
+    }
+
+    #[test]
+    fn save_from_newer_version_is_rejected() {
+        let mut document = unversioned_document();
+        document["version"] = json!(CURRENT_VERSION + 1);
+
+        assert!(migrate(&mut document).is_err());
+    }
+}
@@ -1,10 +1,26 @@
 // Display methods
 
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
-use crate::game::game_state::{GameState, IdleEntityInformation};
+use crate::{
+    config::{Backup, Config},
+    game::{
+        game_state::{
+            AchievementInformation, CampaignInformation, CampaignOutcome, EdictOverview, GameState,
+            IdleEntityInformation, ManagerOverview, PlannedPurchase, PrestigeSummary,
+            RegionInformation, ResearchNodeInformation, SellSummary, SimulationReport,
+            UpgradeAdvice, UpgradeSummary, WorkSummary,
+        },
+        Gold,
+    },
+    profile::ProfileInfo,
+    save::MigrationReport,
+};
 
 const INDENT: &str = " ";
+const PROGRESS_BAR_WIDTH: usize = 20;
+/// Events listed one by one in the status, more are counted per event
+const EVENTS_LISTED: usize = 5;
 
 pub fn display_upgrade_info(entity_info: &IdleEntityInformation, game_state: &GameState) {
     println!("Information to upgrade {}:", entity_info.name);
@@ -17,6 +33,29 @@ pub fn display_upgrade_info(entity_info: &IdleEntityInformation, game_state: &Ga
         entity_info.upgrade_cost_max,
         game_state.game_info.gold
     );
+    if let Some(milestone) = &entity_info.next_milestone {
+        println!(
+            "{:>4}Next milestone at level {} (x{}): {} levels for {:.2} Gold",
+            INDENT,
+            milestone.level,
+            milestone.multiplier,
+            milestone.levels_remaining,
+            milestone.cost
+        );
+    }
+    if entity_info.level > 0 {
+        println!(
+            "{:>4}Selling refunds {:.2} Gold for the last level, {:.2} Gold for all {} levels",
+            INDENT, entity_info.sell_refund_next, entity_info.sell_refund_all, entity_info.level
+        );
+    }
+}
+
+pub fn confirm_sell(summary: &SellSummary) {
+    println!(
+        "Successfully sold {} levels of {} for {:.2} Gold, now at level {}",
+        summary.levels, summary.name, summary.refund, summary.level
+    );
 }
 
 pub fn confirm_upgrade_info(
@@ -30,57 +69,656 @@ pub fn confirm_upgrade_info(
     )
 }
 
-pub fn display_status(game_state: &GameState, delta_time: &Duration) {
+pub fn display_upgrade_all_summary(summaries: &[UpgradeSummary]) {
+    println!("Upgraded all entities:");
+    for summary in summaries {
+        println!(
+            "{:>4}{:<16} +{:>4} levels for {:.2} Gold",
+            INDENT, summary.name, summary.levels, summary.gold_spent
+        );
+    }
     println!(
-        "[Game Status] - Time passed since last check: [{:?}]",
-        delta_time
+        "{:>4}Total: {:.2} Gold",
+        INDENT,
+        summaries
+            .iter()
+            .map(|summary| summary.gold_spent)
+            .sum::<Gold>()
     );
+}
+
+pub fn display_advice(advice: &[UpgradeAdvice]) {
+    println!("Upgrade advice (fastest payback first):");
+    for entry in advice {
+        let affordability = if entry.seconds_until_affordable == 0.0 {
+            "affordable now".to_string()
+        } else {
+            format!(
+                "affordable in {}",
+                format_seconds(entry.seconds_until_affordable)
+            )
+        };
+        println!(
+            "{:>4}{:<16} +{:>3} levels for {:.2} Gold [+{:.2} g/s]: pays back in {}, {}",
+            INDENT,
+            entry.name,
+            entry.levels,
+            entry.cost,
+            entry.gold_per_second_gain,
+            format_seconds(entry.payback_seconds),
+            affordability
+        );
+    }
+}
+
+pub fn display_purchase_plan(plan: &[PlannedPurchase], horizon: &Duration) {
+    if plan.is_empty() {
+        println!(
+            "Nothing becomes affordable within the next {}",
+            format_duration(horizon)
+        );
+        return;
+    }
+
+    println!("Purchase plan for the next {}:", format_duration(horizon));
+    for purchase in plan {
+        println!(
+            "{:>4}in {:<8} {:<16} [{:>4}] -> [{:>4}] for {:.2} Gold [{:.2} g/s]",
+            INDENT,
+            format_seconds(purchase.start_seconds),
+            purchase.name,
+            purchase.from_level,
+            purchase.from_level + purchase.levels,
+            purchase.gold_spent,
+            purchase.gold_per_second_after
+        );
+    }
+}
+
+pub fn display_simulation(report: &SimulationReport) {
+    let duration = Duration::from_secs(report.duration_seconds);
+    println!("Simulation of the next {}:", format_duration(&duration));
+    for step in &report.timeline {
+        let upgrades: Vec<String> = step
+            .upgrades
+            .iter()
+            .map(|summary| format!("{} +{}", summary.name, summary.levels))
+            .collect();
+        println!(
+            "{:>4}[{:>7}] {} [{:.2} g/s]",
+            INDENT,
+            format_duration(&Duration::from_secs(step.elapsed_seconds)),
+            upgrades.join(", "),
+            step.gold_per_second_after
+        );
+    }
     println!(
-        "{:>4}Current Gold: {:.2} Gold",
-        INDENT, game_state.game_info.gold
+        "{:>4}Earned {:.2} Gold, spent {:.2} Gold",
+        INDENT, report.gold_earned, report.gold_spent
     );
+    println!("Final state:");
+    display_game_state(&report.final_state);
+}
+
+pub fn display_achievements(achievements: &[AchievementInformation], game_state: &GameState) {
     println!(
-        "{:>7}{}",
-        INDENT,
-        display_idle_entity_info(&game_state.lumberjack_info)
+        "Achievements: {}/{} unlocked [x{:.2} g/s]",
+        game_state.game_info.achievements_unlocked,
+        achievements.len(),
+        game_state.game_info.achievement_multiplier
     );
+    for achievement in achievements {
+        let marker = if achievement.unlocked_at_unix_seconds.is_some() {
+            "x"
+        } else {
+            " "
+        };
+        println!(
+            "{:>4}[{}] {:<20} {} {:>3.0}% - {} [+{:.0}% g/s]",
+            INDENT,
+            marker,
+            achievement.name,
+            progress_bar(achievement.progress),
+            achievement.progress * 100.0,
+            achievement.description,
+            achievement.bonus * 100.0
+        );
+    }
+}
+
+pub fn display_research(nodes: &[ResearchNodeInformation], research_points: f64) {
+    println!("Research: {:.0} points", research_points);
+    for node in nodes {
+        let marker = if node.researched {
+            "x"
+        } else if node.missing_requirements.is_empty() {
+            " "
+        } else {
+            "-"
+        };
+        println!(
+            "{:>4}[{}] {:<16} {:>8.0} points - {}",
+            INDENT, marker, node.id, node.cost, node.description
+        );
+    }
+}
+
+pub fn display_research_node(node: &ResearchNodeInformation, research_points: f64) {
+    println!("{} ({}):", node.name, node.id);
+    println!("{:>4}{}", INDENT, node.description);
     println!(
-        "{:>7}{}",
-        INDENT,
-        display_idle_entity_info(&game_state.stonemason_info)
+        "{:>4}Cost: {:.0} points [{:.0} points]",
+        INDENT, node.cost, research_points
+    );
+    if node.researched {
+        println!("{:>4}Researched", INDENT);
+    } else if node.missing_requirements.is_empty() {
+        println!("{:>4}All requirements are met", INDENT);
+    } else {
+        println!(
+            "{:>4}Requires: {}",
+            INDENT,
+            node.missing_requirements.join(", ")
+        );
+    }
+}
+
+pub fn confirm_research(node: &ResearchNodeInformation) {
+    println!("Successfully researched {}", node.name);
+}
+
+pub fn display_regions(regions: &[RegionInformation], game_state: &GameState) {
+    println!(
+        "Army strength: {:.0} - Territory: [x{:.2} g/s]",
+        game_state.game_info.army_strength, game_state.game_info.territory_multiplier
     );
+    if let Some(campaign) = &game_state.campaign {
+        display_campaign(campaign);
+    }
+    for region in regions {
+        let marker = if region.conquered { "x" } else { " " };
+        println!(
+            "{:>4}[{}] {:<18} strength {:>6.0} - {:>3.0}% chance, {} for {:.2} Gold [+{:.0}% g/s]",
+            INDENT,
+            marker,
+            region.name,
+            region.difficulty,
+            region.win_chance * 100.0,
+            format_seconds(region.duration_seconds as f64),
+            region.gold_reward,
+            region.territory_bonus * 100.0
+        );
+    }
+}
+
+pub fn confirm_campaign(campaign: &CampaignInformation) {
+    println!("The army marches against {}", campaign.region);
+    display_campaign(campaign);
+}
+
+fn display_campaign(campaign: &CampaignInformation) {
     println!(
-        "{:>7}{}",
+        "{:>4}Campaign against {}: strength {:.0}, {:.0}% chance, decided in {}",
         INDENT,
-        display_idle_entity_info(&game_state.bowmaker_info)
+        campaign.region,
+        campaign.strength,
+        campaign.win_chance * 100.0,
+        format_seconds(campaign.seconds_remaining as f64)
+    );
+}
+
+fn display_campaign_outcome(outcome: &CampaignOutcome) {
+    if outcome.victory {
+        println!(
+            "{:>4}{} was conquered, the army brought back {:.2} Gold",
+            INDENT, outcome.region, outcome.gold_won
+        );
+    } else {
+        let losses: Vec<String> = outcome
+            .losses
+            .iter()
+            .map(|loss| format!("{} {} levels", loss.entity, loss.levels))
+            .collect();
+        println!(
+            "{:>4}The campaign against {} failed, lost {}",
+            INDENT,
+            outcome.region,
+            losses.join(", ")
+        );
+    }
+}
+
+pub fn display_edicts(overview: &EdictOverview, changed: Option<&str>) {
+    if let Some(changed) = changed {
+        println!("Successfully changed the edict {}", changed);
+    }
+    print!("Edict slots: {}", overview.slots);
+    if let Some(next_slot_level) = overview.next_slot_level {
+        print!(" [next slot at King level {}]", next_slot_level);
+    }
+    println!();
+    if overview.cooldown_seconds > 0 {
+        println!(
+            "{:>4}The edicts can be changed again in {}",
+            INDENT,
+            format_seconds(overview.cooldown_seconds as f64)
+        );
+    }
+    for edict in &overview.edicts {
+        let slot = match (edict.slot, edict.active) {
+            (Some(slot), true) => slot.to_string(),
+            // Proclaimed, but the King lost the level of its slot
+            (Some(_), false) => "-".to_string(),
+            (None, _) => " ".to_string(),
+        };
+        println!(
+            "{:>4}[{}] {:<18} {:<18} {}",
+            INDENT, slot, edict.id, edict.name, edict.description
+        );
+    }
+}
+
+pub fn display_work(summary: &WorkSummary) {
+    if summary.too_early {
+        println!(
+            "Still resting, come back in {}. Working early added {} and broke the combo",
+            format_seconds(summary.cooldown_seconds as f64),
+            format_seconds(summary.penalty_seconds as f64)
+        );
+        return;
+    }
+    println!(
+        "Worked for {:.2} Gold [combo {}, x{:.2}]",
+        summary.gold_earned, summary.streak, summary.combo_multiplier
     );
+    if summary.works_left_today == 0 {
+        println!(
+            "{:>4}That is enough work for today, rested again in {}",
+            INDENT,
+            format_seconds(summary.cooldown_seconds as f64)
+        );
+        return;
+    }
     println!(
-        "{:>7}{}",
+        "{:>4}Rested again in {}, keep the combo by working soon after [{} works left today]",
         INDENT,
-        display_idle_entity_info(&game_state.weaponsmith_info)
+        format_seconds(summary.cooldown_seconds as f64),
+        summary.works_left_today
     );
+}
+
+pub fn display_managers(overview: &ManagerOverview, changed: Option<&str>) {
+    if let Some(changed) = changed {
+        println!("Successfully changed the manager of {}", changed);
+    }
+    println!("Managers:");
+    for manager in &overview.managers {
+        if !manager.unlocked {
+            println!(
+                "{:>4}{:<12} research {} to unlock",
+                INDENT, manager.entity, manager.unlocked_by
+            );
+        } else if manager.rules.is_empty() {
+            println!("{:>4}{:<12} no rules", INDENT, manager.entity);
+        } else {
+            println!(
+                "{:>4}{:<12} {}",
+                INDENT,
+                manager.entity,
+                manager.rules.join(", ")
+            );
+        }
+    }
+    if !overview.purchases.is_empty() {
+        println!("Latest purchases:");
+    }
+    for purchase in &overview.purchases {
+        println!(
+            "{:>4}{} +{} levels for {:.2} Gold",
+            INDENT, purchase.entity, purchase.levels, purchase.gold_spent
+        );
+    }
+}
+
+/// A bar like `[#####-----]` for a progress in [0, 1]
+fn progress_bar(progress: f64) -> String {
+    let filled = ((progress * PROGRESS_BAR_WIDTH as f64).floor() as usize).min(PROGRESS_BAR_WIDTH);
+    format!(
+        "[{}{}]",
+        "#".repeat(filled),
+        "-".repeat(PROGRESS_BAR_WIDTH - filled)
+    )
+}
+
+pub fn display_prestige_preview(summary: &PrestigeSummary) {
+    println!("Prestige preview:");
+    display_prestige_summary(summary);
+}
+
+pub fn confirm_prestige(summary: &PrestigeSummary) {
+    if summary.points_gained == 0 {
+        println!("Nothing to gain from a prestige yet, the game was not reset");
+    } else {
+        println!(
+            "Successfully prestiged for {} points",
+            summary.points_gained
+        );
+    }
+    display_prestige_summary(summary);
+}
+
+fn display_prestige_summary(summary: &PrestigeSummary) {
     println!(
-        "{:>7}{}",
+        "{:>4}Lifetime Gold: {:.2} Gold",
+        INDENT, summary.lifetime_gold
+    );
+    println!(
+        "{:>4}Prestige points: {} + {} -> {}",
         INDENT,
-        display_idle_entity_info(&game_state.academic_info)
+        summary.points_before,
+        summary.points_gained,
+        summary.points_before + summary.points_gained
+    );
+    println!(
+        "{:>4}Gold per second multiplier: x{:.2} -> x{:.2}",
+        INDENT, summary.multiplier_before, summary.multiplier_after
+    );
+    println!(
+        "{:>4}Next point at {:.2} lifetime Gold",
+        INDENT, summary.lifetime_gold_for_next_point
+    );
+}
+
+pub fn display_migration_report(report: &MigrationReport, dry_run: bool) {
+    if report.is_up_to_date() {
+        println!("The save is up to date [v{}]", report.to_version);
+        return;
+    }
+
+    if dry_run {
+        println!(
+            "Migrating the save [v{}] -> [v{}] would apply:",
+            report.from_version, report.to_version
+        );
+    } else {
+        println!(
+            "Successfully migrated the save [v{}] -> [v{}]:",
+            report.from_version, report.to_version
+        );
+    }
+    for step in &report.steps {
+        println!("{:>4}{}", INDENT, step);
+    }
+    println!("Changes:");
+    for change in &report.changes {
+        println!("{:>4}{}", INDENT, change);
+    }
+}
+
+pub fn display_profiles(profiles: &[ProfileInfo], current_profile: &str) {
+    if profiles.is_empty() {
+        println!("There are no profiles yet");
+        return;
+    }
+
+    println!("Profiles:");
+    for profile in profiles {
+        let marker = if profile.name == current_profile {
+            "*"
+        } else {
+            " "
+        };
+        let default_note = if profile.is_default { " (default)" } else { "" };
+        println!("{:>4}{} {}{}", INDENT, marker, profile.name, default_note);
+    }
+}
+
+pub fn confirm_init(config: &Config, created_game_directory: bool) {
+    if created_game_directory {
+        println!(
+            "Successfully created a new game directory at {}",
+            config.game_directory.display()
+        );
+    }
+    println!(
+        "Successfully created a new game file for the profile {} at {}",
+        config.profile,
+        config.game_file_path.display()
     );
+}
+
+pub fn confirm_delete(config: &Config, all: bool) {
+    if all {
+        println!(
+            "Successfully removed all files at {}",
+            config.game_directory.display()
+        );
+    } else {
+        println!(
+            "Successfully removed the profile {} and its backups",
+            config.profile
+        );
+    }
+}
+
+pub fn confirm_profile_copy(source: &str, target: &str) {
+    println!("Successfully copied the profile {} to {}", source, target);
+}
+
+pub fn confirm_profile_switch(profile: &str) {
+    println!("Successfully switched the default profile to {}", profile);
+}
+
+pub fn display_backups(backups: &[Backup]) {
+    if backups.is_empty() {
+        println!("There are no backups yet");
+        return;
+    }
+
+    println!("Backups (newest first):");
+    for backup in backups {
+        println!(
+            "{:>4}{} - saved {} ago",
+            INDENT,
+            backup.name,
+            format_duration(&age(backup.created))
+        );
+    }
+}
+
+pub fn confirm_restore(backup: &Backup) {
     println!(
-        "{:>7}{}",
+        "Successfully restored the save from {} [saved {} ago]",
+        backup.name,
+        format_duration(&age(backup.created))
+    );
+}
+
+fn age(time: SystemTime) -> Duration {
+    SystemTime::now().duration_since(time).unwrap_or_default()
+}
+
+/// Formats a duration with its two most significant units, e.g. `3h 25m`
+fn format_duration(duration: &Duration) -> String {
+    let seconds = duration.as_secs();
+    let units = [
+        (seconds / 86_400, "d"),
+        (seconds / 3_600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+
+    let parts: Vec<String> = units
+        .iter()
+        .skip_while(|(value, _)| *value == 0)
+        .take(2)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// Formats seconds like `format_duration`, or `never` if they are infinite
+fn format_seconds(seconds: f64) -> String {
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(duration) => format_duration(&duration),
+        Err(_) => "never".to_string(),
+    }
+}
+
+pub fn display_status(game_state: &GameState, delta_time: &Duration) {
+    println!(
+        "[Game Status] - Time passed since last check: [{:?}]",
+        delta_time
+    );
+    if let Some(clock_skew_seconds) = game_state.game_info.clock_skew_seconds {
+        println!(
+            "{:>4}The system clock went back by {}, no gold was earned until it catches up",
+            INDENT,
+            format_seconds(clock_skew_seconds)
+        );
+    }
+    if let Some(progress) = &game_state.offline_progress {
+        println!(
+            "{:>4}{:.1} of {:.1} hours credited, progress while away stops after {:.1} hours",
+            INDENT,
+            progress.credited_seconds / 3_600.0,
+            progress.away_seconds / 3_600.0,
+            progress.cap_seconds / 3_600.0
+        );
+    }
+    display_game_state(game_state);
+}
+
+fn display_game_state(game_state: &GameState) {
+    println!(
+        "{:>4}Current Gold: {:.2} Gold",
+        INDENT, game_state.game_info.gold
+    );
+    println!(
+        "{:>4}Lifetime Gold: {:.2} Gold - Prestige: {} points [x{:.2} g/s]",
         INDENT,
-        display_idle_entity_info(&game_state.catapult_info)
+        game_state.game_info.lifetime_gold,
+        game_state.game_info.prestige_points,
+        game_state.game_info.prestige_multiplier
     );
     println!(
-        "{:>7}{}",
+        "{:>4}Achievements: {} unlocked [x{:.2} g/s]",
         INDENT,
-        display_idle_entity_info(&game_state.king_info)
+        game_state.game_info.achievements_unlocked,
+        game_state.game_info.achievement_multiplier
     );
+    if game_state.game_info.work_cooldown_seconds > 0 {
+        println!(
+            "{:>4}Work: rested in {} [combo {}]",
+            INDENT,
+            format_seconds(game_state.game_info.work_cooldown_seconds as f64),
+            game_state.game_info.work_streak
+        );
+    } else {
+        println!(
+            "{:>4}Work: ready [combo {}]",
+            INDENT, game_state.game_info.work_streak
+        );
+    }
+    if !game_state.game_info.active_edicts.is_empty() {
+        println!(
+            "{:>4}Edicts: {}",
+            INDENT,
+            game_state.game_info.active_edicts.join(", ")
+        );
+    }
+    for achievement in &game_state.new_achievements {
+        println!("{:>4}Achievement unlocked: {}", INDENT, achievement);
+    }
+    if let Some(outcome) = &game_state.campaign_outcome {
+        display_campaign_outcome(outcome);
+    }
+    if let Some(campaign) = &game_state.campaign {
+        display_campaign(campaign);
+    }
+    display_events(game_state);
+    for purchase in &game_state.manager_purchases {
+        println!(
+            "{:>4}Manager bought {} +{} levels for {:.2} Gold",
+            INDENT, purchase.name, purchase.levels, purchase.gold_spent
+        );
+    }
+    for resource in &game_state.resources {
+        println!(
+            "{:>4}{:<10} {:>12.2} [+{:.2}/s, -{:.2}/s]",
+            INDENT,
+            resource.name,
+            resource.stockpile,
+            resource.production_per_second,
+            resource.consumption_per_second
+        );
+    }
+    for entity_info in &game_state.entity_infos {
+        println!("{:>7}{}", INDENT, display_idle_entity_info(entity_info));
+    }
+}
+
+/// Events since the last check, counted per event once there are many
+fn display_events(game_state: &GameState) {
+    if game_state.events.len() <= EVENTS_LISTED {
+        for event in &game_state.events {
+            println!("{:>4}Event: {} - {}", INDENT, event.name, event.description);
+        }
+    } else {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for event in &game_state.events {
+            match counts.iter_mut().find(|(name, _)| *name == event.name) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((&event.name, 1)),
+            }
+        }
+        println!(
+            "{:>4}Events: {} since the last check ({})",
+            INDENT,
+            game_state.events.len(),
+            counts
+                .iter()
+                .map(|(name, count)| format!("{} x{}", name, count))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+    for event in &game_state.active_events {
+        println!(
+            "{:>4}{} lasts another {}",
+            INDENT,
+            event.name,
+            format_seconds(event.seconds_remaining as f64)
+        );
+    }
+}
+
+/// Comma separated list of all entity names
+pub fn entity_names(game_state: &GameState) -> String {
+    game_state
+        .entity_infos
+        .iter()
+        .map(|entity_info| entity_info.name.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ")
 }
 
 fn display_idle_entity_info(entity: &IdleEntityInformation) -> String {
-    format!(
+    let mut info = format!(
         "[{:>4}] {:<16} {:>8.02} g/s.",
         entity.level,
         entity.name.as_str(),
         entity.gold_per_second
-    )
+    );
+    if entity.throughput < 1.0 {
+        info.push_str(&format!(
+            " (short of resources, {:.0}% capacity)",
+            entity.throughput * 100.0
+        ));
+    }
+    info
 }